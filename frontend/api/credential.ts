@@ -1,12 +1,15 @@
-import { PubSubSessionV1, PubSubSessionV2 } from '@kiltprotocol/kilt-extension-api'
 import type { IClaim } from "@kiltprotocol/types"
 import { api } from './backend';
+import { DappSession } from './session';
 
-export async function fetchCredential(session: PubSubSessionV1 | PubSubSessionV2, claim: IClaim) {
+export async function fetchCredential({ session, sessionId }: DappSession, claim: IClaim) {
   const credentialUrl = `credential`;
 
 
-  const getTermsResponse = await api.post(`${credentialUrl}/terms`, { json: claim });
+  const getTermsResponse = await api.post(`${credentialUrl}/terms`, {
+    json: claim,
+    headers: { 'X-Session-Id': sessionId },
+  });
 
   if (getTermsResponse.status !== 200) {
     throw Error("Failed to get terms", await getTermsResponse.json())
@@ -1,7 +1,13 @@
 import { InjectedWindowProvider, PubSubSessionV1, PubSubSessionV2 } from '@kiltprotocol/kilt-extension-api'
 import { api } from './backend';
 
-export async function getSession(provider: InjectedWindowProvider): Promise<PubSubSessionV1 | PubSubSessionV2> {
+export interface DappSession {
+  session: PubSubSessionV1 | PubSubSessionV2
+  // names the session in the `X-Session-Id` header, a browser can keep several sessions
+  sessionId: string
+}
+
+export async function getSession(provider: InjectedWindowProvider): Promise<DappSession> {
   if (!provider) {
     throw new Error('No provider')
   }
@@ -27,5 +33,7 @@ export async function getSession(provider: InjectedWindowProvider): Promise<PubS
     throw new Error('No valid Session.')
   }
 
-  return session
+  const { sessionId } = await sessionVerification.json<any>()
+
+  return { session, sessionId }
 }
@@ -1,3 +1,4 @@
+use actix_web::cookie::Key;
 use clap::Parser;
 use serde::Deserialize;
 use sodiumoxide::crypto::box_::SecretKey;
@@ -7,7 +8,15 @@ use subxt::{
     utils::AccountId32,
 };
 
-use crate::kilt::{well_known_did_configuration::WellKnownDidConfigData, KiltConfig};
+use crate::{
+    device::file_manager::get_session_key,
+    kilt::{
+        attestation_policy::AttestationPolicy,
+        did_uri::{DidKeyUri, DidUri},
+        well_known_did_configuration::WellKnownDidConfigData,
+        KiltConfig,
+    },
+};
 
 #[derive(Deserialize, Debug, Clone, Parser)]
 pub struct Configuration {
@@ -32,13 +41,50 @@ pub struct Configuration {
     #[clap(env)]
     pub well_known_seed: String,
     #[clap(env)]
-    pub session_encryption_public_key_uri: String,
+    pub session_encryption_public_key_uri: DidKeyUri,
     #[clap(env)]
     session_encryption_key_secret: String,
     #[clap(env)]
     attestation_seed: String,
     #[clap(env)]
     pub attestation_did_seed: String,
+    /// Seed of the delegation key of the attester DID. Defaults to the attestation seed.
+    #[clap(env, long)]
+    delegation_seed: Option<String>,
+    /// If set, calls posted to the payment route are queued until an admin approves them.
+    #[clap(env, long)]
+    pub require_call_approval: bool,
+    /// If set, every attestation request waits for the approval of an admin.
+    #[clap(env, long)]
+    pub require_attestation_review: bool,
+    /// Comma separated CType hashes the verifier requests credentials for.
+    #[clap(env, long, value_delimiter = ',')]
+    pub verifier_ctypes: Vec<String>,
+    /// Comma separated DIDs whose attestations the verifier accepts. Without attesters every attester is trusted.
+    #[clap(env, long, value_delimiter = ',')]
+    pub verifier_trusted_attesters: Vec<DidUri>,
+    /// Path of the JSON file with the attestation policy. Without a policy every request is attested.
+    #[clap(env, long)]
+    attestation_policy_path: Option<String>,
+    /// Maximum age in seconds of a received encrypted message. Older messages are rejected.
+    #[clap(env, long, default_value_t = 300)]
+    pub message_max_age: u64,
+    /// Hex encoded key of at least 64 bytes signing the session cookies. Defaults to a key stored on the device.
+    #[clap(env, long)]
+    session_key_secret: Option<String>,
+    /// Lifetime of a Credential API session in seconds.
+    #[clap(env, long, default_value_t = 600)]
+    pub session_ttl: i64,
+    /// Path of the JSON file the sessions are persisted to. Without a path sessions are only kept in memory.
+    #[clap(env, long)]
+    pub session_store_path: Option<String>,
+    /// If set, the session cookie is only sent over HTTPS.
+    #[clap(env, long)]
+    pub session_cookie_secure: bool,
+    /// Bearer token of the admin routes, e.g. to approve queued calls and attestation requests.
+    /// Without a token the admin routes are disabled.
+    #[clap(env, long)]
+    pub admin_token: Option<String>,
 }
 
 impl Configuration {
@@ -60,6 +106,28 @@ impl Configuration {
         let pair = sr25519::Pair::from_string_with_seed(&self.attestation_seed, None)?.0;
         Ok(PairSigner::new(pair))
     }
+    pub fn get_delegation_signer(&self) -> anyhow::Result<PairSigner<KiltConfig, sr25519::Pair>> {
+        let seed = self
+            .delegation_seed
+            .as_deref()
+            .unwrap_or(&self.attestation_seed);
+        let pair = sr25519::Pair::from_string_with_seed(seed, None)?.0;
+        Ok(PairSigner::new(pair))
+    }
+    pub fn get_attestation_policy(&self) -> anyhow::Result<AttestationPolicy> {
+        match &self.attestation_policy_path {
+            Some(path) => AttestationPolicy::load(path),
+            None => Ok(AttestationPolicy::default()),
+        }
+    }
+    pub fn get_session_key(&self) -> anyhow::Result<Key> {
+        let raw_key = match &self.session_key_secret {
+            Some(secret) => hex::decode(secret.trim_start_matches("0x"))?,
+            None => get_session_key()?,
+        };
+        Key::try_from(raw_key.as_slice())
+            .map_err(|_| anyhow::anyhow!("Session key must have at least 64 bytes"))
+    }
     pub fn get_did(&self) -> anyhow::Result<AccountId32> {
         let pair = sr25519::Pair::from_string_with_seed(&self.attestation_did_seed, None)?.0;
         Ok(pair.public().into())
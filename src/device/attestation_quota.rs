@@ -0,0 +1,68 @@
+//! Daily attestation counts of the CTypes. An attestation is reserved before it is submitted, so concurrent
+//! requests cannot exceed the quota of a CType. Once the attestation is on chain the reservation is written to
+//! the quota file, if the submission fails it is released again.
+
+use std::collections::HashMap;
+
+use crate::device::{
+    error::DeviceError,
+    file_manager::{get_daily_attestation_count, increment_daily_attestation_count},
+};
+
+/// Attestation counted against the daily quota of its CType while it is submitted.
+#[derive(Debug)]
+pub struct QuotaReservation {
+    ctype_hash: String,
+    date: String,
+}
+
+/// Attestations being submitted, keyed by CType hash and date.
+/// All reads and writes of the quota file go through this type, it has to be kept behind a lock.
+#[derive(Debug, Default)]
+pub struct AttestationQuota {
+    reserved: HashMap<(String, String), u32>,
+}
+
+impl AttestationQuota {
+    /// Returns the number of attestations of the CType issued on `date`, including the ones being submitted.
+    pub fn count(&self, ctype_hash: &str, date: &str) -> Result<u32, DeviceError> {
+        let reserved = self
+            .reserved
+            .get(&(ctype_hash.to_lowercase(), date.to_string()))
+            .copied()
+            .unwrap_or_default();
+        Ok(get_daily_attestation_count(ctype_hash, date)? + reserved)
+    }
+
+    /// Counts an attestation of the CType on `date` until it is released.
+    pub fn reserve(&mut self, ctype_hash: &str, date: &str) -> QuotaReservation {
+        let reservation = QuotaReservation {
+            ctype_hash: ctype_hash.to_lowercase(),
+            date: date.to_string(),
+        };
+        *self
+            .reserved
+            .entry((reservation.ctype_hash.clone(), reservation.date.clone()))
+            .or_default() += 1;
+        reservation
+    }
+
+    /// Ends the reservation. An issued attestation is written to the quota file, a failed one is dropped.
+    pub fn release(
+        &mut self,
+        reservation: QuotaReservation,
+        issued: bool,
+    ) -> Result<(), DeviceError> {
+        let key = (reservation.ctype_hash, reservation.date);
+        if let Some(reserved) = self.reserved.get_mut(&key) {
+            *reserved = reserved.saturating_sub(1);
+            if *reserved == 0 {
+                self.reserved.remove(&key);
+            }
+        }
+        if issued {
+            increment_daily_attestation_count(&key.0, &key.1)?;
+        }
+        Ok(())
+    }
+}
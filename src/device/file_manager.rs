@@ -1,5 +1,12 @@
-use std::{fs, io, path::Path, str::FromStr};
-use subxt::ext::sp_core::crypto::Ss58Codec;
+use std::{
+    collections::HashMap,
+    fs, io,
+    io::Write,
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::Path,
+    str::FromStr,
+};
+use uuid::Uuid;
 
 use crate::{
     device::{
@@ -7,19 +14,34 @@ use crate::{
         error::DeviceError,
         key_manager::{KeyManager, PairKeyManager},
     },
-    dto::Credential,
-    kilt::did_helper::{ADDRESS_FORMAT, DID_PREFIX},
+    dto::{
+        AttestationRecord, AttestationReview, ClaimerMessage, Credential, CredentialStatus,
+        DailyAttestationCount, StoredCredential,
+    },
+    kilt::did_uri::DidUri,
 };
 
 const KEY_FILE_PATH: &str = "./keys.json";
-pub const BASE_CLAIM_PATH: &str = "./base_claim.json";
+const BASE_CLAIM_PATH: &str = "./base_claim.json";
+const CREDENTIAL_STORE_PATH: &str = "./credentials.json";
+const ATTESTATION_RECORDS_PATH: &str = "./attestation_records.json";
+const CREDENTIAL_STATUS_PATH: &str = "./credential_status.json";
+const CTYPE_CATALOG_PATH: &str = "./ctypes.json";
+const ATTESTATION_QUOTA_PATH: &str = "./attestation_quota.json";
+const ATTESTATION_REVIEWS_PATH: &str = "./attestation_reviews.json";
+const SESSION_KEY_PATH: &str = "./session_key";
+const CLAIMER_MESSAGES_PATH: &str = "./claimer_messages.json";
+/// Messages not fetched by the claimer within this time are dropped, in seconds.
+const CLAIMER_MESSAGE_MAX_AGE: i64 = 30 * 24 * 60 * 60;
+/// Number of messages kept for all claimers together. The oldest messages are dropped first.
+const MAX_CLAIMER_MESSAGES: usize = 1_000;
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct KeysFileStructure {
     pub payment_account_seed: String,
     pub did_auth_seed: String,
-    pub did: String,
+    pub did: DidUri,
 }
 
 /// Save the key file to [KEY_FILE_PATH].
@@ -59,12 +81,7 @@ fn generate_key_file_struct() -> Result<KeysFileStructure, DeviceError> {
     let auth_mnemonic = bip39::Mnemonic::from_entropy(&auth_random_seed)?;
 
     let manager = PairKeyManager::new(&payment_mnemonic.to_string(), &auth_mnemonic.to_string())?;
-    let raw_did = manager
-        .get_did_auth_signer()
-        .account_id()
-        .to_ss58check_with_version(ADDRESS_FORMAT.into());
-
-    let did = format!("{}{}", DID_PREFIX, raw_did);
+    let did = DidUri::full(manager.get_did_auth_signer().account_id().clone());
 
     Ok(KeysFileStructure {
         payment_account_seed: payment_mnemonic.to_string(),
@@ -88,14 +105,7 @@ pub fn reset_did_keys() -> Result<PairKeyManager, DeviceError> {
         let manager =
             PairKeyManager::new(&keys_file.payment_account_seed, &keys_file.did_auth_seed)?;
 
-        let raw_did = manager
-            .get_did_auth_signer()
-            .account_id()
-            .to_ss58check_with_version(ADDRESS_FORMAT.into());
-
-        let did = format!("{}{}", DID_PREFIX, raw_did);
-
-        keys_file.did = did;
+        keys_file.did = DidUri::full(manager.get_did_auth_signer().account_id().clone());
         save_key_file(&keys_file)?;
 
         Ok(manager)
@@ -108,15 +118,254 @@ pub fn reset_did_keys() -> Result<PairKeyManager, DeviceError> {
     }
 }
 
-/// Reads the content in [BASE_CLAIM_PATH]
-pub fn get_claim_content() -> Result<Credential, DeviceError> {
-    let base_claim = std::fs::read_to_string(BASE_CLAIM_PATH)?;
-    let claim: Credential = serde_json::from_str(&base_claim)?;
-    Ok(claim)
+/// Reads all credentials in [CREDENTIAL_STORE_PATH] keyed by root hash.
+fn read_credential_store() -> Result<HashMap<String, StoredCredential>, DeviceError> {
+    if !Path::new(CREDENTIAL_STORE_PATH).exists() {
+        return Ok(HashMap::new());
+    }
+    let store = std::fs::read_to_string(CREDENTIAL_STORE_PATH)?;
+    Ok(serde_json::from_str(&store)?)
+}
+
+/// Takes a credential in the former single credential file [BASE_CLAIM_PATH] over into [CREDENTIAL_STORE_PATH].
+/// Runs once on startup, the store is written right away so the migrated credential keeps its `created_at`.
+pub fn migrate_credential_store() -> Result<(), DeviceError> {
+    if Path::new(CREDENTIAL_STORE_PATH).exists() || !Path::new(BASE_CLAIM_PATH).exists() {
+        return Ok(());
+    }
+
+    let base_claim: Credential = serde_json::from_str(&std::fs::read_to_string(BASE_CLAIM_PATH)?)?;
+    let root_hash = base_claim.root_hash.to_lowercase();
+    let mut stored = StoredCredential::new(base_claim);
+    if Path::new(CREDENTIAL_STATUS_PATH).exists() {
+        let mut statuses: HashMap<String, CredentialStatus> =
+            serde_json::from_str(&std::fs::read_to_string(CREDENTIAL_STATUS_PATH)?)?;
+        if let Some(status) = statuses.remove(&root_hash) {
+            stored.status = status;
+        }
+    }
+    log::info!(
+        "Credential {} taken over into the credential store",
+        root_hash
+    );
+    write_credential_store(&HashMap::from([(root_hash, stored)]))
+}
+
+fn write_credential_store(store: &HashMap<String, StoredCredential>) -> Result<(), DeviceError> {
+    let string_content = serde_json::to_string(store)?;
+    std::fs::write(CREDENTIAL_STORE_PATH, string_content).map_err(DeviceError::from)
+}
+
+/// Returns all credentials of the device, the oldest first.
+pub fn get_credentials() -> Result<Vec<StoredCredential>, DeviceError> {
+    let mut credentials: Vec<_> = read_credential_store()?.into_values().collect();
+    credentials.sort_by_key(|stored| stored.created_at);
+    Ok(credentials)
+}
+
+/// Returns the credential with the root hash from [CREDENTIAL_STORE_PATH].
+pub fn get_credential(root_hash: &str) -> Result<Option<StoredCredential>, DeviceError> {
+    Ok(read_credential_store()?.remove(&root_hash.to_lowercase()))
+}
+
+/// Adds or replaces the credential in [CREDENTIAL_STORE_PATH].
+/// Writes to the store must hold the credential store lock of the app state.
+pub fn save_credential(stored: StoredCredential) -> Result<(), DeviceError> {
+    let mut store = read_credential_store()?;
+    store.insert(stored.credential.root_hash.to_lowercase(), stored);
+    write_credential_store(&store)
+}
+
+/// Removes the credential from [CREDENTIAL_STORE_PATH]. Returns `None` if there is no credential with the root hash.
+/// Writes to the store must hold the credential store lock of the app state.
+pub fn remove_credential(root_hash: &str) -> Result<Option<StoredCredential>, DeviceError> {
+    let mut store = read_credential_store()?;
+    let removed = store.remove(&root_hash.to_lowercase());
+    write_credential_store(&store)?;
+    Ok(removed)
+}
+
+/// Removes all credentials, e.g. when the DID of the device changes.
+pub fn clear_credentials() -> Result<(), DeviceError> {
+    for path in [
+        CREDENTIAL_STORE_PATH,
+        BASE_CLAIM_PATH,
+        CREDENTIAL_STATUS_PATH,
+    ] {
+        if Path::new(path).exists() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads all records in [ATTESTATION_RECORDS_PATH]. Returns an empty list if no record was written yet.
+pub fn get_attestation_records() -> Result<Vec<AttestationRecord>, DeviceError> {
+    if !Path::new(ATTESTATION_RECORDS_PATH).exists() {
+        return Ok(vec![]);
+    }
+    let records = std::fs::read_to_string(ATTESTATION_RECORDS_PATH)?;
+    Ok(serde_json::from_str(&records)?)
+}
+
+/// Appends the record to [ATTESTATION_RECORDS_PATH].
+/// Writes to the records must hold the attestation record lock of the app state.
+pub fn save_attestation_record(record: AttestationRecord) -> Result<(), DeviceError> {
+    let mut records = get_attestation_records()?;
+    records.push(record);
+    let string_content = serde_json::to_string(&records)?;
+    std::fs::write(ATTESTATION_RECORDS_PATH, string_content).map_err(DeviceError::from)
+}
+
+/// Sets the status of the credential with the root hash in [CREDENTIAL_STORE_PATH].
+/// Writes to the store must hold the credential store lock of the app state.
+pub fn save_credential_status(
+    root_hash: &str,
+    status: CredentialStatus,
+) -> Result<(), DeviceError> {
+    let mut store = read_credential_store()?;
+    if let Some(stored) = store.get_mut(&root_hash.to_lowercase()) {
+        stored.status = status;
+        write_credential_store(&store)?;
+    }
+    Ok(())
+}
+
+/// Reads the known CTypes per CType hash from [CTYPE_CATALOG_PATH].
+pub fn get_ctype_catalog() -> Result<HashMap<String, serde_json::Value>, DeviceError> {
+    if !Path::new(CTYPE_CATALOG_PATH).exists() {
+        return Ok(HashMap::new());
+    }
+    let catalog = std::fs::read_to_string(CTYPE_CATALOG_PATH)?;
+    Ok(serde_json::from_str(&catalog)?)
+}
+
+/// Adds the CType to [CTYPE_CATALOG_PATH].
+pub fn save_ctype(ctype_hash: &str, ctype: &serde_json::Value) -> Result<(), DeviceError> {
+    let mut catalog = get_ctype_catalog()?;
+    catalog.insert(ctype_hash.to_lowercase(), ctype.clone());
+    let string_content = serde_json::to_string(&catalog)?;
+    std::fs::write(CTYPE_CATALOG_PATH, string_content).map_err(DeviceError::from)
+}
+
+/// Returns the number of attestations of the CType issued on `date`, read from [ATTESTATION_QUOTA_PATH].
+pub fn get_daily_attestation_count(ctype_hash: &str, date: &str) -> Result<u32, DeviceError> {
+    if !Path::new(ATTESTATION_QUOTA_PATH).exists() {
+        return Ok(0);
+    }
+    let count: DailyAttestationCount =
+        serde_json::from_str(&std::fs::read_to_string(ATTESTATION_QUOTA_PATH)?)?;
+    if count.date != date {
+        return Ok(0);
+    }
+    Ok(count
+        .counts
+        .get(&ctype_hash.to_lowercase())
+        .copied()
+        .unwrap_or_default())
+}
+
+/// Counts an attestation of the CType on `date` in [ATTESTATION_QUOTA_PATH]. The counts of previous days are dropped.
+pub fn increment_daily_attestation_count(ctype_hash: &str, date: &str) -> Result<(), DeviceError> {
+    let mut count = if Path::new(ATTESTATION_QUOTA_PATH).exists() {
+        serde_json::from_str(&std::fs::read_to_string(ATTESTATION_QUOTA_PATH)?)?
+    } else {
+        DailyAttestationCount::default()
+    };
+    if count.date != date {
+        count = DailyAttestationCount {
+            date: date.to_string(),
+            counts: HashMap::new(),
+        };
+    }
+    *count.counts.entry(ctype_hash.to_lowercase()).or_default() += 1;
+    let string_content = serde_json::to_string(&count)?;
+    std::fs::write(ATTESTATION_QUOTA_PATH, string_content).map_err(DeviceError::from)
+}
+
+/// Reads all attestation requests waiting for a review from [ATTESTATION_REVIEWS_PATH].
+pub fn get_attestation_reviews() -> Result<Vec<AttestationReview>, DeviceError> {
+    if !Path::new(ATTESTATION_REVIEWS_PATH).exists() {
+        return Ok(vec![]);
+    }
+    let reviews = std::fs::read_to_string(ATTESTATION_REVIEWS_PATH)?;
+    Ok(serde_json::from_str(&reviews)?)
+}
+
+fn save_attestation_reviews(reviews: &[AttestationReview]) -> Result<(), DeviceError> {
+    let string_content = serde_json::to_string(reviews)?;
+    std::fs::write(ATTESTATION_REVIEWS_PATH, string_content).map_err(DeviceError::from)
 }
 
-/// saves the credential in [BASE_CLAIM_PATH]
-pub fn save_claim_content(content: &Credential) -> Result<(), DeviceError> {
-    let string_content = serde_json::to_string(content)?;
-    std::fs::write(BASE_CLAIM_PATH, &string_content).map_err(DeviceError::from)
+/// Appends the attestation request to [ATTESTATION_REVIEWS_PATH].
+/// Writes to the reviews must hold the attestation review lock of the app state.
+pub fn save_attestation_review(review: AttestationReview) -> Result<(), DeviceError> {
+    let mut reviews = get_attestation_reviews()?;
+    reviews.push(review);
+    save_attestation_reviews(&reviews)
+}
+
+/// Removes the attestation request from [ATTESTATION_REVIEWS_PATH] once it is approved or rejected.
+/// Writes to the reviews must hold the attestation review lock of the app state.
+pub fn remove_attestation_review(id: &Uuid) -> Result<(), DeviceError> {
+    let mut reviews = get_attestation_reviews()?;
+    reviews.retain(|review| review.id != *id);
+    save_attestation_reviews(&reviews)
+}
+
+fn get_claimer_messages() -> Result<Vec<ClaimerMessage>, DeviceError> {
+    if !Path::new(CLAIMER_MESSAGES_PATH).exists() {
+        return Ok(vec![]);
+    }
+    let messages = std::fs::read_to_string(CLAIMER_MESSAGES_PATH)?;
+    Ok(serde_json::from_str(&messages)?)
+}
+
+fn save_claimer_messages(messages: &[ClaimerMessage]) -> Result<(), DeviceError> {
+    let string_content = serde_json::to_string(messages)?;
+    std::fs::write(CLAIMER_MESSAGES_PATH, string_content).map_err(DeviceError::from)
+}
+
+/// Appends the message to [CLAIMER_MESSAGES_PATH]. Expired messages are dropped.
+/// Writes to the mailbox must hold the claimer mailbox lock of the app state.
+pub fn save_claimer_message(message: ClaimerMessage) -> Result<(), DeviceError> {
+    let now = chrono::Utc::now().timestamp();
+    let mut messages = get_claimer_messages()?;
+    messages.retain(|message| message.created_at + CLAIMER_MESSAGE_MAX_AGE > now);
+    messages.push(message);
+    if messages.len() > MAX_CLAIMER_MESSAGES {
+        messages.drain(..messages.len() - MAX_CLAIMER_MESSAGES);
+    }
+    save_claimer_messages(&messages)
+}
+
+/// Removes and returns the messages for the DID from [CLAIMER_MESSAGES_PATH].
+/// Writes to the mailbox must hold the claimer mailbox lock of the app state.
+pub fn take_claimer_messages(receiver: &DidUri) -> Result<Vec<ClaimerMessage>, DeviceError> {
+    let messages = get_claimer_messages()?;
+    let (taken, kept): (Vec<_>, Vec<_>) = messages
+        .into_iter()
+        .partition(|message| message.receiver.to_full() == receiver.to_full());
+    if !taken.is_empty() {
+        save_claimer_messages(&kept)?;
+    }
+    Ok(taken)
+}
+
+/// Reads the key signing the session cookies from [SESSION_KEY_PATH]. A new key is created on the first start.
+/// The file is only readable by the owner.
+pub fn get_session_key() -> anyhow::Result<Vec<u8>> {
+    if Path::new(SESSION_KEY_PATH).exists() {
+        fs::set_permissions(SESSION_KEY_PATH, fs::Permissions::from_mode(0o600))?;
+        let key = fs::read_to_string(SESSION_KEY_PATH)?;
+        return Ok(hex::decode(key.trim())?);
+    }
+    let key = [get_random_bytes(32)?, get_random_bytes(32)?].concat();
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(SESSION_KEY_PATH)?;
+    file.write_all(hex::encode(&key).as_bytes())?;
+    Ok(key)
 }
@@ -1,7 +1,9 @@
+pub mod attestation_quota;
 pub mod crypto;
 pub mod error;
 pub mod file_manager;
 pub mod key_manager;
+pub mod session_store;
 
 pub use error::DeviceError;
 pub use file_manager::{
@@ -0,0 +1,211 @@
+//! Server-side storage for the sessions of the Credential API.
+//! The session cookie only carries an opaque id, the session state stays on the server.
+//! With a path the sessions are written to disk, so they survive a restart of the server. Changes are written by
+//! a background task shortly after they happen, so requests never wait for the file.
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::cookie::time::Duration;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration as StdDuration,
+};
+use tokio::sync::Notify;
+
+use crate::device::error::DeviceError;
+
+const SESSION_ID_LENGTH: usize = 64;
+/// Changes within this delay are written to the file together.
+const PERSIST_DELAY: StdDuration = StdDuration::from_secs(1);
+
+type SessionState = HashMap<String, String>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredSession {
+    state: SessionState,
+    /// Unix timestamp in seconds
+    expires_at: i64,
+}
+
+impl StoredSession {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// Session store keeping the sessions in memory, optionally backed by a JSON file.
+#[derive(Debug, Clone, Default)]
+pub struct ServerSessionStore {
+    sessions: Arc<Mutex<HashMap<String, StoredSession>>>,
+    path: Option<PathBuf>,
+    /// Wakes the task writing the file. `None` without a file.
+    changed: Option<Arc<Notify>>,
+}
+
+impl ServerSessionStore {
+    /// Creates the store and loads the sessions which are not expired from the file at `path`.
+    /// With a path a task writing the changes to the file is spawned, so this must run inside a Tokio runtime.
+    pub fn new(path: Option<String>) -> Result<Self, DeviceError> {
+        let path = path.map(PathBuf::from);
+        let mut sessions: HashMap<String, StoredSession> = match &path {
+            Some(path) if Path::new(path).exists() => {
+                serde_json::from_str(&std::fs::read_to_string(path)?)?
+            }
+            _ => HashMap::new(),
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        sessions.retain(|_, session| !session.is_expired(now));
+
+        let store = ServerSessionStore {
+            sessions: Arc::new(Mutex::new(sessions)),
+            changed: path.as_ref().map(|_| Arc::new(Notify::new())),
+            path,
+        };
+        if let (Some(path), Some(changed)) = (&store.path, &store.changed) {
+            tokio::spawn(write_changes(
+                store.sessions.clone(),
+                path.clone(),
+                changed.clone(),
+            ));
+        }
+        Ok(store)
+    }
+
+    fn sessions(
+        &self,
+    ) -> anyhow::Result<std::sync::MutexGuard<'_, HashMap<String, StoredSession>>> {
+        self.sessions
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Session store lock is poisoned"))
+    }
+
+    /// Removes expired sessions and schedules writing the remaining ones to the file, if the store has one.
+    fn persist(&self, sessions: &mut HashMap<String, StoredSession>) {
+        let now = chrono::Utc::now().timestamp();
+        sessions.retain(|_, session| !session.is_expired(now));
+
+        if let Some(changed) = &self.changed {
+            changed.notify_one();
+        }
+    }
+
+    /// Writes the sessions to the file right away, e.g. before the server shuts down.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        if let Some(path) = &self.path {
+            let content = serde_json::to_string(&*self.sessions()?)?;
+            std::fs::write(path, content)?;
+        }
+        Ok(())
+    }
+
+    fn insert(&self, session_key: &str, state: SessionState, ttl: &Duration) -> anyhow::Result<()> {
+        let mut sessions = self.sessions()?;
+        sessions.insert(
+            session_key.to_string(),
+            StoredSession {
+                state,
+                expires_at: chrono::Utc::now().timestamp() + ttl.whole_seconds(),
+            },
+        );
+        self.persist(&mut sessions);
+        Ok(())
+    }
+}
+
+/// Writes the sessions to the file whenever they changed. A single writer keeps the writes in order, the file
+/// itself is written on the blocking thread pool.
+async fn write_changes(
+    sessions: Arc<Mutex<HashMap<String, StoredSession>>>,
+    path: PathBuf,
+    changed: Arc<Notify>,
+) {
+    loop {
+        changed.notified().await;
+        tokio::time::sleep(PERSIST_DELAY).await;
+
+        let content = match sessions.lock() {
+            Ok(sessions) => serde_json::to_string(&*sessions),
+            Err(_) => {
+                log::error!("Session store lock is poisoned, sessions are no longer written");
+                return;
+            }
+        };
+        let content = match content {
+            Ok(content) => content,
+            Err(e) => {
+                log::error!("Could not serialize the sessions: {}", e);
+                continue;
+            }
+        };
+
+        let path = path.clone();
+        match tokio::task::spawn_blocking(move || std::fs::write(path, content)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::error!("Could not write the sessions: {}", e),
+            Err(e) => log::error!("Writing the sessions failed: {}", e),
+        }
+    }
+}
+
+fn generate_session_key() -> Result<SessionKey, anyhow::Error> {
+    let session_key: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SESSION_ID_LENGTH)
+        .map(char::from)
+        .collect();
+    Ok(session_key.try_into()?)
+}
+
+impl SessionStore for ServerSessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let sessions = self.sessions().map_err(LoadError::Other)?;
+        let now = chrono::Utc::now().timestamp();
+        Ok(sessions
+            .get(session_key.as_ref())
+            .filter(|session| !session.is_expired(now))
+            .map(|session| session.state.clone()))
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let session_key = generate_session_key().map_err(SaveError::Other)?;
+        self.insert(session_key.as_ref(), session_state, ttl)
+            .map_err(SaveError::Other)?;
+        Ok(session_key)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        self.insert(session_key.as_ref(), session_state, ttl)
+            .map_err(UpdateError::Other)?;
+        Ok(session_key)
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> anyhow::Result<()> {
+        let mut sessions = self.sessions()?;
+        if let Some(session) = sessions.get_mut(session_key.as_ref()) {
+            session.expires_at = chrono::Utc::now().timestamp() + ttl.whole_seconds();
+        }
+        self.persist(&mut sessions);
+        Ok(())
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> anyhow::Result<()> {
+        let mut sessions = self.sessions()?;
+        sessions.remove(session_key.as_ref());
+        self.persist(&mut sessions);
+        Ok(())
+    }
+}
@@ -1,21 +1,17 @@
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct Claim {
-    #[serde(rename = "cTypeHash")]
-    pub ctype_hash: String,
-    contents: serde_json::Value,
-    pub owner: String,
-}
+use std::collections::HashMap;
+use uuid::Uuid;
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct Credential {
-    pub claim: Claim,
-    claim_nonce_map: serde_json::Value,
-    claim_hashes: Vec<String>,
-    delegation_id: Option<String>,
-    legitimations: Option<Vec<Credential>>,
-    pub root_hash: String,
-}
+use crate::{
+    kilt::{
+        attestation::AttestationStatus,
+        attestation_policy::PolicyViolation,
+        delegation::DelegationPermission,
+        did_uri::{DidKeyUri, DidUri},
+    },
+    routes::dto::{CredentialPresentation, EncryptedMessage, RequestAttestationMessageContent},
+};
+
+pub use crate::routes::dto::Credential;
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct JWTHeader {
@@ -37,13 +33,21 @@ pub struct JWTBody {
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct DidAddress {
-    pub did: String,
+    pub did: DidUri,
+    #[serde(rename = "web3Name", skip_serializing_if = "Option::is_none", default)]
+    pub web3_name: Option<String>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct TxResponse {
     pub tx: String,
-    pub did: String,
+    pub did: DidUri,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct ServiceEndpointUpdate {
+    pub types: Vec<String>,
+    pub urls: Vec<String>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -54,8 +58,8 @@ pub struct PayerAddress {
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UseCaseRegistrationBody {
-    pub did_url: String,
-    pub presentation: Credential,
+    pub did_url: DidUri,
+    pub presentation: CredentialPresentation,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -63,3 +67,172 @@ pub struct UseCaseRegistrationBody {
 pub struct UseCaseResponse {
     pub use_case: String,
 }
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AttestationAction {
+    Revoke,
+    Remove,
+    ReclaimDeposit,
+}
+
+/// Outcome of an attestation management transaction.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationRecord {
+    pub root_hash: String,
+    pub action: AttestationAction,
+    pub success: bool,
+    pub tx: Option<String>,
+    pub error: Option<String>,
+    pub created_at: i64,
+}
+
+/// Number of attestations per CType hash issued on the given day (UTC).
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyAttestationCount {
+    pub date: String,
+    pub counts: HashMap<String, u32>,
+}
+
+/// Encrypted message waiting until the claimer fetches it, e.g. the rejection of a reviewed attestation request.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimerMessage {
+    pub receiver: DidUri,
+    pub message: EncryptedMessage,
+    pub created_at: i64,
+}
+
+/// Attestation request which waits for a manual review.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationReview {
+    pub id: Uuid,
+    pub sender_key_uri: DidKeyUri,
+    pub message_id: String,
+    pub content: RequestAttestationMessageContent,
+    pub violations: Vec<PolicyViolation>,
+    pub created_at: i64,
+}
+
+/// Credential held by the device, with its status and its attestation as seen on chain.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredCredential {
+    pub credential: Credential,
+    pub status: CredentialStatus,
+    pub attestation: Option<AttestationStatus>,
+    pub created_at: i64,
+}
+
+impl StoredCredential {
+    pub fn new(credential: Credential) -> Self {
+        StoredCredential {
+            credential,
+            status: CredentialStatus::Pending,
+            attestation: None,
+            created_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    pub fn attester(&self) -> Option<&DidUri> {
+        self.attestation
+            .as_ref()
+            .map(|attestation| &attestation.attester)
+    }
+}
+
+/// Filter for the credentials of the device. Unset fields match every credential.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct CredentialFilter {
+    #[serde(rename = "cTypeHash")]
+    pub ctype_hash: Option<String>,
+    pub attester: Option<DidUri>,
+    pub status: Option<CredentialStatus>,
+}
+
+impl CredentialFilter {
+    pub fn matches(&self, stored: &StoredCredential) -> bool {
+        let ctype_matches = self.ctype_hash.as_ref().map_or(true, |ctype_hash| {
+            ctype_hash.eq_ignore_ascii_case(&stored.credential.claim.ctype_hash)
+        });
+        let attester_matches = self.attester.as_ref().map_or(true, |attester| {
+            stored.attester().map(DidUri::to_full) == Some(attester.to_full())
+        });
+        let status_matches = self.status.map_or(true, |status| status == stored.status);
+        ctype_matches && attester_matches && status_matches
+    }
+}
+
+/// Status of a credential held by the device, as seen on chain.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CredentialStatus {
+    Pending,
+    Attested,
+    Revoked,
+    Removed,
+}
+
+/// Notification pushed to the UI whenever the status of a device credential changes.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialNotification {
+    pub root_hash: String,
+    pub status: CredentialStatus,
+    pub block_hash: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct DelegationHierarchyRequest {
+    #[serde(rename = "cTypeHash")]
+    pub ctype_hash: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DelegationSigningRequest {
+    pub delegate: DidUri,
+    pub permissions: Vec<DelegationPermission>,
+}
+
+/// Data the delegate has to sign with its authentication key to accept the delegation.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DelegationSigningData {
+    pub delegation_id: String,
+    pub delegate: DidUri,
+    pub hash: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DelegateKeyType {
+    Sr25519,
+    Ed25519,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DelegateSignature {
+    pub key_type: DelegateKeyType,
+    pub signature: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddDelegationRequest {
+    pub delegation_id: String,
+    pub delegate: DidUri,
+    pub permissions: Vec<DelegationPermission>,
+    pub signature: DelegateSignature,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DelegationTxResponse {
+    pub tx: String,
+    pub delegation_id: String,
+}
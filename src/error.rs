@@ -3,7 +3,10 @@ use std::io::ErrorKind;
 
 use crate::{
     device::DeviceError,
-    kilt::error::{CredentialAPIError, DidError, TxError, UseCaseAPIError},
+    kilt::error::{
+        CTypeError, CredentialAPIError, DelegationError, DidError, DidLookupError,
+        ServiceEndpointError, TxError, UseCaseAPIError, Web3NameError,
+    },
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -22,8 +25,10 @@ pub enum ServerError {
     URL(#[from] url::ParseError),
     #[error("Login error: {0}")]
     Login(&'static str),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(&'static str),
     #[error("Subxt error: {0}")]
-    Subxt(#[from] subxt::Error),
+    Subxt(Box<subxt::Error>),
     #[error("Hex error: {0}")]
     Hex(#[from] hex::FromHexError),
     #[error("Server error: {0}")]
@@ -34,6 +39,12 @@ pub enum ServerError {
     UseCaseAPI(#[from] UseCaseAPIError),
 }
 
+impl From<subxt::Error> for ServerError {
+    fn from(e: subxt::Error) -> Self {
+        ServerError::Subxt(Box::new(e))
+    }
+}
+
 impl ResponseError for DeviceError {
     fn error_response(&self) -> actix_web::HttpResponse<actix_web::body::BoxBody> {
         if self.status_code() != StatusCode::INTERNAL_SERVER_ERROR {
@@ -70,11 +81,58 @@ impl ResponseError for TxError {
         match self {
             TxError::Did(did_error) => match did_error {
                 DidError::NotFound(_) => StatusCode::NOT_FOUND,
-                DidError::Format(_) => StatusCode::BAD_REQUEST,
+                DidError::LightDid(_) | DidError::Uri(_) => StatusCode::BAD_REQUEST,
             },
 
             TxError::Subxt(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            TxError::Format(_) | TxError::Hex(_) => StatusCode::BAD_REQUEST,
+            TxError::Format(_) | TxError::Hex(_) | TxError::Decode(_) => StatusCode::BAD_REQUEST,
+            TxError::PendingCallQueueFull => StatusCode::TOO_MANY_REQUESTS,
+            TxError::AttestationReviewInProgress(_) => StatusCode::CONFLICT,
+            TxError::PendingCallNotFound(_)
+            | TxError::AttestationNotFound(_)
+            | TxError::AttestationReviewNotFound(_) => StatusCode::NOT_FOUND,
+            TxError::ServiceEndpoint(ServiceEndpointError::NotFound(_)) => StatusCode::NOT_FOUND,
+            TxError::ServiceEndpoint(ServiceEndpointError::AlreadyExists(_)) => {
+                StatusCode::CONFLICT
+            }
+            TxError::ServiceEndpoint(_) => StatusCode::BAD_REQUEST,
+            TxError::Web3Name(web3_name_error) => match web3_name_error {
+                Web3NameError::Length { .. } | Web3NameError::InvalidCharacters(_) => {
+                    StatusCode::BAD_REQUEST
+                }
+                Web3NameError::Unavailable(_) | Web3NameError::AlreadyClaimed(_) => {
+                    StatusCode::CONFLICT
+                }
+                Web3NameError::NotClaimed => StatusCode::NOT_FOUND,
+                Web3NameError::Subxt(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            TxError::DidLookup(did_lookup_error) => match did_lookup_error {
+                DidLookupError::Address(_) => StatusCode::BAD_REQUEST,
+                DidLookupError::AlreadyLinked(_) => StatusCode::CONFLICT,
+                DidLookupError::NotLinked(_) => StatusCode::NOT_FOUND,
+                DidLookupError::StorageKey(_) | DidLookupError::Subxt(_) => {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            },
+            TxError::Delegation(delegation_error) => match delegation_error {
+                DelegationError::NotFound(_) => StatusCode::NOT_FOUND,
+                DelegationError::Revoked(_)
+                | DelegationError::NotOwner(_)
+                | DelegationError::MissingPermission(_) => StatusCode::FORBIDDEN,
+                DelegationError::CTypeMismatch(_)
+                | DelegationError::Id(_)
+                | DelegationError::Signature(_) => StatusCode::BAD_REQUEST,
+                DelegationError::Subxt(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            TxError::CType(ctype_error) => match ctype_error {
+                CTypeError::NotFound(_) => StatusCode::NOT_FOUND,
+                CTypeError::AlreadyRegistered(_) => StatusCode::CONFLICT,
+                CTypeError::Schema(_)
+                | CTypeError::Validation(_)
+                | CTypeError::Hash(_)
+                | CTypeError::Json(_) => StatusCode::BAD_REQUEST,
+                CTypeError::Subxt(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            },
         }
     }
 }
@@ -91,7 +149,14 @@ impl ResponseError for CredentialAPIError {
         match self {
             &CredentialAPIError::Challenge(..)
             | &CredentialAPIError::LightDID(..)
+            | &CredentialAPIError::DidUri(..)
+            | &CredentialAPIError::Integrity(..)
+            | &CredentialAPIError::UnexpectedMessage(..)
+            | &CredentialAPIError::Verifier(..)
+            | &CredentialAPIError::AttestationMismatch(..)
+            | &CredentialAPIError::Message(..)
             | &CredentialAPIError::Did(..) => StatusCode::BAD_REQUEST,
+            CredentialAPIError::CredentialNotFound(..) => StatusCode::NOT_FOUND,
             CredentialAPIError::Attestation(..) | CredentialAPIError::Subxt(..) => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
@@ -109,7 +174,7 @@ impl ResponseError for UseCaseAPIError {
 
     fn status_code(&self) -> StatusCode {
         match self {
-            UseCaseAPIError::NotFound => StatusCode::NOT_FOUND,
+            UseCaseAPIError::NotFound | UseCaseAPIError::NoCredential => StatusCode::NOT_FOUND,
             UseCaseAPIError::Format => StatusCode::BAD_REQUEST,
         }
     }
@@ -135,6 +200,7 @@ impl ResponseError for ServerError {
             ServerError::CredentialAPI(e) => e.status_code(),
             ServerError::UseCaseAPI(e) => e.status_code(),
             ServerError::Json(..) | ServerError::Hex(..) => StatusCode::BAD_REQUEST,
+            ServerError::Unauthorized(..) => StatusCode::UNAUTHORIZED,
             ServerError::HttpClient(..)
             | ServerError::HttpClientHeader(..)
             | ServerError::URL(..)
@@ -3,10 +3,7 @@ use rand::{distributions::Alphanumeric, Rng};
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
 use sha2::{Digest, Sha512};
 use subxt::{
-    ext::{
-        sp_core::{crypto::Ss58Codec, sr25519},
-        sp_runtime::MultiSignature,
-    },
+    ext::{sp_core::sr25519, sp_runtime::MultiSignature},
     tx::{PairSigner, Signer},
     OnlineClient,
 };
@@ -16,9 +13,11 @@ use crate::{
     dto::*,
     error::ServerError,
     kilt::{
-        did_helper::{query_did_doc, ADDRESS_FORMAT},
+        did_helper::query_did_doc,
+        did_uri::{DidKeyUri, DidUri},
         KiltConfig,
     },
+    routes::dto::CredentialPresentation,
 };
 
 pub fn hex_encode<T: AsRef<[u8]>>(data: T) -> String {
@@ -106,21 +105,21 @@ fn get_id_token(url_str: &str) -> Result<String, ServerError> {
 }
 
 fn get_encoded_jwt_parts(
-    did: String,
-    key_uri: String,
+    did: &DidUri,
+    key_uri: &DidKeyUri,
     nonce: String,
 ) -> Result<(String, String), ServerError> {
     let jwt_header = JWTHeader {
         alg: "EdDSA".to_string(),
         typ: "JWT".to_string(),
-        kid: key_uri,
+        kid: key_uri.to_string(),
         crv: "ed25519".to_string(),
         kty: "ed25519".to_string(),
     };
 
     let jwt_body = JWTBody {
-        iss: did.clone(),
-        sub: did.clone(),
+        iss: did.to_string(),
+        sub: did.to_string(),
         nonce,
         nbf: chrono::Utc::now().timestamp(),
         exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp(),
@@ -165,14 +164,13 @@ pub async fn login_to_open_did(
 ) -> Result<String, ServerError> {
     let (client, nonce) = request_login(client_id, auth_endpoint, redirect_url).await?;
 
-    let did_auth_account_id = signer.account_id();
-    let did = did_auth_account_id.to_ss58check_with_version(ADDRESS_FORMAT.into());
+    let did = DidUri::full(signer.account_id().clone());
     let did_doc = query_did_doc(&did, kilt_api).await?;
 
     let kid = hex_encode(did_doc.authentication_key.as_bytes());
-    let key_uri = format!("{}#{}", did, kid);
+    let key_uri = did.key_uri(&kid);
 
-    let (jwt_header_encoded, jwt_body_encoded) = get_encoded_jwt_parts(did, key_uri, nonce)?;
+    let (jwt_header_encoded, jwt_body_encoded) = get_encoded_jwt_parts(&did, &key_uri, nonce)?;
 
     let jwt_signature_encoded =
         get_encoded_jwt_signature(&jwt_header_encoded, &jwt_body_encoded, signer);
@@ -265,8 +263,8 @@ pub async fn get_credentials_from_attester(
 
 pub async fn post_use_case_participation(
     use_case_url: &str,
-    did_url: &str,
-    presentation: Credential,
+    did_url: &DidUri,
+    presentation: CredentialPresentation,
 ) -> Result<(), ServerError> {
     let mut headers = reqwest::header::HeaderMap::new();
     headers.insert(CONTENT_TYPE, "application/json".parse()?);
@@ -278,7 +276,7 @@ pub async fn post_use_case_participation(
     let url = format!("{}/api/v1/device/register", use_case_url);
 
     let registration_body = UseCaseRegistrationBody {
-        did_url: did_url.to_string(),
+        did_url: did_url.clone(),
         presentation,
     };
 
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+use subxt::OnlineClient;
+
+use crate::kilt::{
+    did_helper::format_ss58_address,
+    did_uri::DidUri,
+    runtime::{self, runtime_types::runtime_common::authorization::AuthorizationId},
+    KiltConfig,
+};
+
+/// On-chain state of an attestation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationStatus {
+    pub root_hash: String,
+    pub attester: DidUri,
+    pub ctype_hash: String,
+    pub revoked: bool,
+    pub delegation_id: Option<String>,
+    pub deposit_owner: String,
+}
+
+/// Fetches the attestation for the claim hash. Returns `None` if there is no attestation on chain.
+pub async fn query_attestation(
+    claim_hash: H256,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Option<AttestationStatus>, subxt::Error> {
+    let attestation_key = runtime::storage().attestation().attestations(claim_hash);
+    let details = chain_client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&attestation_key)
+        .await?;
+
+    Ok(details.map(|details| AttestationStatus {
+        root_hash: format!("{:?}", claim_hash),
+        attester: DidUri::full(details.attester),
+        ctype_hash: format!("{:?}", details.ctype_hash),
+        revoked: details.revoked,
+        delegation_id: details.authorization_id.map(|id| match id {
+            AuthorizationId::Delegation(delegation_id) => format!("{:?}", delegation_id),
+        }),
+        deposit_owner: format_ss58_address(&details.deposit.owner),
+    }))
+}
@@ -0,0 +1,137 @@
+//! Rules deciding which attestation requests are attested without a human in the loop.
+//! Every CType listed in the policy can restrict the claim owners, require fixed values for claim properties
+//! and limit the number of attestations per day. Requests violating the policy are queued for a manual review.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::kilt::did_uri::DidUri;
+
+/// Rules for the claims of one CType.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CTypeRule {
+    /// DIDs allowed to request an attestation. A `*` matches any sequence of characters. Empty allows every owner.
+    #[serde(default)]
+    pub allowed_owners: Vec<String>,
+    /// Claim properties which must have exactly the given value.
+    #[serde(default)]
+    pub required_fields: Map<String, Value>,
+    /// Maximum number of attestations per day (UTC).
+    #[serde(default)]
+    pub daily_quota: Option<u32>,
+}
+
+/// Attestation policy keyed by CType hash.
+/// Without a policy file every CType is allowed and no rules apply.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationPolicy {
+    #[serde(rename = "cTypes")]
+    pub ctypes: Option<HashMap<String, CTypeRule>>,
+}
+
+/// Reason why a request was not attested automatically.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PolicyViolation {
+    CTypeNotAllowed {
+        #[serde(rename = "cTypeHash")]
+        ctype_hash: String,
+    },
+    OwnerNotAllowed {
+        owner: String,
+    },
+    FieldMismatch {
+        field: String,
+    },
+    QuotaExceeded {
+        quota: u32,
+    },
+}
+
+impl AttestationPolicy {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let policy = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&policy)?)
+    }
+
+    /// Returns the rule of the CType. `None` if the CType is not allowed by the policy.
+    pub fn rule(&self, ctype_hash: &str) -> Option<CTypeRule> {
+        match &self.ctypes {
+            Some(ctypes) => ctypes
+                .iter()
+                .find(|(hash, _)| hash.eq_ignore_ascii_case(ctype_hash))
+                .map(|(_, rule)| rule.clone()),
+            None => Some(CTypeRule::default()),
+        }
+    }
+
+    /// Checks the claim against the policy. `attested_today` is the number of attestations of the CType issued today.
+    /// An empty list means the claim can be attested right away.
+    pub fn check(
+        &self,
+        ctype_hash: &str,
+        owner: &DidUri,
+        contents: &Value,
+        attested_today: u32,
+    ) -> Vec<PolicyViolation> {
+        let Some(rule) = self.rule(ctype_hash) else {
+            return vec![PolicyViolation::CTypeNotAllowed {
+                ctype_hash: ctype_hash.to_string(),
+            }];
+        };
+
+        let mut violations = vec![];
+
+        let owner = owner.to_full().to_string();
+        if !rule.allowed_owners.is_empty()
+            && !rule
+                .allowed_owners
+                .iter()
+                .any(|pattern| matches_pattern(pattern, &owner))
+        {
+            violations.push(PolicyViolation::OwnerNotAllowed { owner });
+        }
+
+        for (field, value) in &rule.required_fields {
+            if contents.get(field) != Some(value) {
+                violations.push(PolicyViolation::FieldMismatch {
+                    field: field.clone(),
+                });
+            }
+        }
+
+        if let Some(quota) = rule.daily_quota {
+            if attested_today >= quota {
+                violations.push(PolicyViolation::QuotaExceeded { quota });
+            }
+        }
+
+        violations
+    }
+}
+
+/// Matches the value against a pattern where `*` stands for any sequence of characters.
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(first) = parts.next() else {
+        return true;
+    };
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
@@ -0,0 +1,200 @@
+//! Background task which follows finalized blocks and updates the status of the device credentials.
+//! Blocks finalized while the watcher is not connected are not replayed. Instead the attestations of all device
+//! credentials are read at the finalized head after every (re)connect, so no status change is missed.
+
+use futures::StreamExt;
+use sp_core::H256;
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use subxt::{blocks::Block, OnlineClient};
+use tokio::sync::{broadcast, Mutex};
+
+use crate::{
+    device::{
+        error::DeviceError,
+        file_manager::{get_credentials, save_credential_status},
+    },
+    dto::{CredentialNotification, CredentialStatus},
+    kilt::{connect, runtime, KiltConfig},
+};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// Watches the attestation events forever. If the connection to the chain breaks, the watcher reconnects.
+pub async fn watch_attestation_events(
+    wss_endpoint: String,
+    notifications: broadcast::Sender<CredentialNotification>,
+    credential_store_lock: Arc<Mutex<()>>,
+) {
+    loop {
+        if let Err(e) =
+            follow_finalized_blocks(&wss_endpoint, &notifications, &credential_store_lock).await
+        {
+            log::error!("Attestation watcher stopped: {}", e);
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+        log::info!("Restarting attestation watcher");
+    }
+}
+
+/// Returns the root hashes of all credentials the device holds.
+fn get_watched_root_hashes() -> Result<HashSet<H256>, DeviceError> {
+    Ok(get_credentials()?
+        .iter()
+        .filter_map(|stored| parse_root_hash(&stored.credential.root_hash))
+        .collect())
+}
+
+fn parse_root_hash(root_hash: &str) -> Option<H256> {
+    let bytes = hex::decode(root_hash.trim_start_matches("0x").trim()).ok()?;
+    (bytes.len() == 32).then(|| H256::from_slice(&bytes))
+}
+
+/// Saves the new status of the credential and tells the UI about it.
+async fn publish_status(
+    claim_hash: H256,
+    status: CredentialStatus,
+    block_hash: H256,
+    notifications: &broadcast::Sender<CredentialNotification>,
+    credential_store_lock: &Mutex<()>,
+) -> Result<(), DeviceError> {
+    let root_hash = format!("{:?}", claim_hash);
+    log::info!("Credential {} changed status to {:?}", root_hash, status);
+    {
+        let _store = credential_store_lock.lock().await;
+        save_credential_status(&root_hash, status)?;
+    }
+
+    // Sending only fails if no UI is subscribed, which is fine.
+    let _ = notifications.send(CredentialNotification {
+        root_hash,
+        status,
+        block_hash: format!("{:?}", block_hash),
+    });
+    Ok(())
+}
+
+/// Reads the attestations of all device credentials at the finalized head and updates the statuses which changed
+/// while the watcher was not following the chain. Returns the number of the block the statuses were read at.
+async fn reconcile_statuses(
+    chain_client: &OnlineClient<KiltConfig>,
+    notifications: &broadcast::Sender<CredentialNotification>,
+    credential_store_lock: &Mutex<()>,
+) -> anyhow::Result<u64> {
+    let block_hash = chain_client.rpc().finalized_head().await?;
+    let block_number = chain_client
+        .rpc()
+        .header(Some(block_hash))
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Finalized block {:?} not found", block_hash))?
+        .number;
+    let storage = chain_client.storage().at(block_hash);
+
+    for stored in get_credentials()? {
+        let Some(claim_hash) = parse_root_hash(&stored.credential.root_hash) else {
+            continue;
+        };
+        let attestation = match storage
+            .fetch(&runtime::storage().attestation().attestations(claim_hash))
+            .await
+        {
+            Ok(attestation) => attestation,
+            Err(e) => {
+                log::warn!("Could not read the attestation of {:?}: {}", claim_hash, e);
+                continue;
+            }
+        };
+
+        let status = match (attestation, stored.status) {
+            (Some(details), _) if details.revoked => CredentialStatus::Revoked,
+            (Some(_), _) => CredentialStatus::Attested,
+            // a credential which was never attested is still pending
+            (None, CredentialStatus::Pending) => CredentialStatus::Pending,
+            (None, _) => CredentialStatus::Removed,
+        };
+        if status != stored.status {
+            publish_status(
+                claim_hash,
+                status,
+                block_hash,
+                notifications,
+                credential_store_lock,
+            )
+            .await?;
+        }
+    }
+    Ok(block_number)
+}
+
+/// Applies the attestation events of the block to the device credentials.
+async fn process_block(
+    block: &Block<KiltConfig, OnlineClient<KiltConfig>>,
+    notifications: &broadcast::Sender<CredentialNotification>,
+    credential_store_lock: &Mutex<()>,
+) -> anyhow::Result<()> {
+    let watched_root_hashes = get_watched_root_hashes()?;
+    if watched_root_hashes.is_empty() {
+        return Ok(());
+    }
+
+    let events = block.events().await?;
+    // The claim hash is the second field of all attestation events.
+    let mut changes = vec![];
+
+    for event in events.find::<runtime::attestation::events::AttestationCreated>() {
+        changes.push((event?.1, CredentialStatus::Attested));
+    }
+    for event in events.find::<runtime::attestation::events::AttestationRevoked>() {
+        changes.push((event?.1, CredentialStatus::Revoked));
+    }
+    for event in events.find::<runtime::attestation::events::AttestationRemoved>() {
+        changes.push((event?.1, CredentialStatus::Removed));
+    }
+
+    for (claim_hash, status) in changes {
+        if watched_root_hashes.contains(&claim_hash) {
+            publish_status(
+                claim_hash,
+                status,
+                block.hash(),
+                notifications,
+                credential_store_lock,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn follow_finalized_blocks(
+    wss_endpoint: &str,
+    notifications: &broadcast::Sender<CredentialNotification>,
+    credential_store_lock: &Mutex<()>,
+) -> anyhow::Result<()> {
+    let chain_client = connect(wss_endpoint).await?;
+    // subscribe before reconciling, so no block is finalized in between unnoticed
+    let mut blocks = chain_client.blocks().subscribe_finalized().await?;
+    let reconciled_at =
+        reconcile_statuses(&chain_client, notifications, credential_store_lock).await?;
+
+    log::info!("Attestation watcher started at block {}", reconciled_at);
+
+    while let Some(block) = blocks.next().await {
+        let block = block?;
+        // the state of these blocks is already covered by the reconciliation
+        if block.number() <= reconciled_at {
+            continue;
+        }
+
+        // a block which cannot be processed must not stop the watcher
+        if let Err(e) = process_block(&block, notifications, credential_store_lock).await {
+            log::error!(
+                "Could not process block {} ({:?}): {}",
+                block.number(),
+                block.hash(),
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
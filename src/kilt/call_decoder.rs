@@ -0,0 +1,82 @@
+//! Decodes SCALE encoded runtime calls into a human-readable representation.
+//! The decoding uses the metadata bundled with the binary, so it works without a chain connection.
+
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use subxt::{
+    ext::{codec::Decode, scale_value},
+    Metadata,
+};
+
+use crate::kilt::error::TxError;
+
+#[cfg(feature = "spiritnet")]
+const BUNDLED_METADATA: &[u8] = include_bytes!("../../metadata/spiritnet_11405.scale");
+
+#[cfg(not(feature = "spiritnet"))]
+const BUNDLED_METADATA: &[u8] = include_bytes!("../../metadata/peregrine_11405.scale");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedCall {
+    pub pallet: String,
+    pub call: String,
+    pub args: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Returns the decoded bundled metadata. The metadata is only decoded once.
+pub fn bundled_metadata() -> Result<&'static Metadata, TxError> {
+    static METADATA: OnceLock<Metadata> = OnceLock::new();
+
+    if let Some(metadata) = METADATA.get() {
+        return Ok(metadata);
+    }
+
+    let metadata = Metadata::decode(&mut &BUNDLED_METADATA[..])
+        .map_err(|e| TxError::Decode(format!("Bundled metadata is invalid: {e}")))?;
+    Ok(METADATA.get_or_init(|| metadata))
+}
+
+/// Decodes the call bytes into pallet name, call name and named arguments.
+pub fn decode_call(call: &[u8]) -> Result<DecodedCall, TxError> {
+    let metadata = bundled_metadata()?;
+
+    let [pallet_index, call_index, encoded_args @ ..] = call else {
+        return Err(TxError::Decode("Call is too short".to_string()));
+    };
+
+    let pallet = metadata
+        .pallet_by_index(*pallet_index)
+        .ok_or_else(|| TxError::Decode(format!("Unknown pallet index: {pallet_index}")))?;
+
+    let variant = pallet.call_variant_by_index(*call_index).ok_or_else(|| {
+        TxError::Decode(format!(
+            "Unknown call index {call_index} in pallet {}",
+            pallet.name()
+        ))
+    })?;
+
+    let mut input = encoded_args;
+    let mut args = serde_json::Map::new();
+
+    for (position, field) in variant.fields.iter().enumerate() {
+        let value = scale_value::scale::decode_as_type(&mut input, field.ty.id, metadata.types())
+            .map_err(|e| TxError::Decode(format!("Argument {position} is invalid: {e}")))?;
+        let name = field.name.clone().unwrap_or_else(|| position.to_string());
+        let json_value = serde_json::to_value(value.remove_context())
+            .map_err(|e| TxError::Decode(e.to_string()))?;
+        args.insert(name, json_value);
+    }
+
+    if !input.is_empty() {
+        return Err(TxError::Decode(format!(
+            "{} trailing bytes after call arguments",
+            input.len()
+        )));
+    }
+
+    Ok(DecodedCall {
+        pallet: pallet.name().to_string(),
+        call: variant.name.clone(),
+        args,
+    })
+}
@@ -0,0 +1,278 @@
+//! Hashing scheme of KILT credentials.
+//! Every claim property is normalized into a JSON-LD statement, hashed and salted with a nonce.
+//! The root hash is the hash over the sorted salted hashes, the root hashes of the legitimations and the delegation id.
+
+use blake2::{Blake2b, Digest};
+use hmac::digest::typenum::U32;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::kilt::{ctype::CTYPE_ID_PREFIX, did_uri::DidUri, error::CredentialIntegrityError};
+
+type Blake2b256 = Blake2b<U32>;
+
+fn hex_encode<T: AsRef<[u8]>>(data: T) -> String {
+    format!("0x{}", hex::encode(data.as_ref()))
+}
+
+fn hex_decode(data: &str) -> Result<Vec<u8>, CredentialIntegrityError> {
+    hex::decode(data.trim_start_matches("0x"))
+        .map_err(|_| CredentialIntegrityError::Hex(data.to_string()))
+}
+
+/// Returns the hex encoded hash of the statement.
+pub fn hash_statement(statement: &str) -> String {
+    let mut hasher = Blake2b256::new();
+    hasher.update(statement);
+    hex_encode(hasher.finalize())
+}
+
+/// Salts the hex encoded statement hash with the nonce.
+pub fn salted_hash(nonce: &str, statement_hash: &str) -> String {
+    let mut hasher = Blake2b256::new();
+    hasher.update(nonce);
+    hasher.update(statement_hash);
+    hex_encode(hasher.finalize())
+}
+
+/// Normalizes the claim into one JSON-LD statement for the owner and one per property.
+pub fn claim_statements(
+    ctype_hash: &str,
+    owner: &DidUri,
+    contents: &Value,
+) -> Result<Vec<String>, CredentialIntegrityError> {
+    let contents = contents
+        .as_object()
+        .ok_or(CredentialIntegrityError::Contents)?;
+
+    let mut statements = vec![statement("@id", Value::String(owner.to_string()))?];
+    for (key, value) in contents {
+        statements.push(property_statement(ctype_hash, key, value)?);
+    }
+    Ok(statements)
+}
+
+/// Normalizes one claim property into its JSON-LD statement.
+pub fn property_statement(
+    ctype_hash: &str,
+    key: &str,
+    value: &Value,
+) -> Result<String, CredentialIntegrityError> {
+    statement(
+        &format!("{}{}#{}", CTYPE_ID_PREFIX, ctype_hash, key),
+        value.clone(),
+    )
+}
+
+fn statement(key: &str, value: Value) -> Result<String, CredentialIntegrityError> {
+    let mut statement = Map::new();
+    statement.insert(key.to_string(), value);
+    serde_json::to_string(&statement).map_err(|_| CredentialIntegrityError::Contents)
+}
+
+/// Checks that every statement of the claim is covered by a salted hash of the credential.
+pub fn verify_claim_hashes(
+    statements: &[String],
+    nonce_map: &HashMap<String, String>,
+    claim_hashes: &[String],
+) -> Result<(), CredentialIntegrityError> {
+    for statement in statements {
+        let statement_hash = hash_statement(statement);
+        let nonce = nonce_map
+            .get(&statement_hash)
+            .ok_or_else(|| CredentialIntegrityError::MissingNonce(statement_hash.clone()))?;
+        let salted = salted_hash(nonce, &statement_hash);
+        if !claim_hashes
+            .iter()
+            .any(|hash| hash.eq_ignore_ascii_case(&salted))
+        {
+            return Err(CredentialIntegrityError::ClaimHash(statement_hash));
+        }
+    }
+    Ok(())
+}
+
+/// Data the claimer signs for a presentation: the root hash followed by the challenge of the verifier.
+/// Like the KILT SDK, a hex challenge is signed as bytes and any other challenge as UTF-8.
+pub fn presentation_signing_data(
+    root_hash: &str,
+    challenge: Option<&str>,
+) -> Result<Vec<u8>, CredentialIntegrityError> {
+    let mut data = hex_decode(root_hash)?;
+    if let Some(challenge) = challenge {
+        match challenge
+            .strip_prefix("0x")
+            .and_then(|hex| hex::decode(hex).ok())
+        {
+            Some(bytes) => data.extend(bytes),
+            None => data.extend(challenge.as_bytes()),
+        }
+    }
+    Ok(data)
+}
+
+/// Calculates the root hash over the claim hashes, the root hashes of the legitimations and the delegation id.
+pub fn calculate_root_hash(
+    claim_hashes: &[String],
+    legitimation_root_hashes: &[&str],
+    delegation_id: Option<&str>,
+) -> Result<String, CredentialIntegrityError> {
+    let mut hasher = Blake2b256::new();
+    for hash in claim_hashes
+        .iter()
+        .map(String::as_str)
+        .chain(legitimation_root_hashes.iter().copied())
+        .chain(delegation_id)
+    {
+        hasher.update(hex_decode(hash)?);
+    }
+    Ok(hex_encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    const OWNER: &str = "did:kilt:4siJtc4dYq2gPre8Xj6KJcSjVAdi1gmjctUzjf3AwrtNnhvy";
+    const EMAIL_CTYPE_HASH: &str =
+        "0x3291bb126e33b4862d421bfaa1d2f272e6cdfc4f96658988fbcffea8914bd9ac";
+
+    const OWNER_STATEMENT_HASH: &str =
+        "0xb1c5613aefedbebdb1a766a2541984bb8d8e8d337448e4b47eea0476c36a9cab";
+    const EMAIL_STATEMENT_HASH: &str =
+        "0x6e44590d76cef45f0fbab749fc384655a3d952a61eabdcdd2893b180cfa60acc";
+    const OWNER_NONCE: &str = "a9a8c1f2-6f0e-4a53-9c4e-0d3b1f6a2e71";
+    const EMAIL_NONCE: &str = "5f2c7b9e-3d1a-4e8f-b6c2-8a9d0e1f2b34";
+    /// Salted hashes of the owner and the email statement, sorted.
+    const CLAIM_HASHES: [&str; 2] = [
+        "0x950893b104723d28d44620768903ca4c24baa6c7de0f6db0d1d8abe7ab12fbad",
+        "0xea6bee7b58ced37967cef643e9a22b791bf6316df6557588f4e698791dee494f",
+    ];
+    const ROOT_HASH: &str = "0x09c6e9d5cebd8507b7e79db1462df7e5ad7665dc5c2671ebf1dd1b54b36c5176";
+
+    fn email_claim_statements(contents: Value) -> Vec<String> {
+        claim_statements(
+            EMAIL_CTYPE_HASH,
+            &DidUri::from_str(OWNER).unwrap(),
+            &contents,
+        )
+        .unwrap()
+    }
+
+    fn nonce_map() -> HashMap<String, String> {
+        HashMap::from([
+            (OWNER_STATEMENT_HASH.to_string(), OWNER_NONCE.to_string()),
+            (EMAIL_STATEMENT_HASH.to_string(), EMAIL_NONCE.to_string()),
+        ])
+    }
+
+    fn claim_hashes() -> Vec<String> {
+        CLAIM_HASHES.iter().map(|hash| hash.to_string()).collect()
+    }
+
+    #[test]
+    fn normalizes_the_claim_into_statements() {
+        let statements = email_claim_statements(json!({ "Email": "alice@kilt.io" }));
+        assert_eq!(
+            statements,
+            vec![
+                format!(r#"{{"@id":"{}"}}"#, OWNER),
+                format!(
+                    r#"{{"kilt:ctype:{}#Email":"alice@kilt.io"}}"#,
+                    EMAIL_CTYPE_HASH
+                ),
+            ]
+        );
+        assert_eq!(hash_statement(&statements[0]), OWNER_STATEMENT_HASH);
+        assert_eq!(hash_statement(&statements[1]), EMAIL_STATEMENT_HASH);
+    }
+
+    #[test]
+    fn salts_the_statement_hashes() {
+        assert_eq!(
+            salted_hash(OWNER_NONCE, OWNER_STATEMENT_HASH),
+            CLAIM_HASHES[0]
+        );
+        assert_eq!(
+            salted_hash(EMAIL_NONCE, EMAIL_STATEMENT_HASH),
+            CLAIM_HASHES[1]
+        );
+    }
+
+    #[test]
+    fn verifies_the_claim_hashes() {
+        let statements = email_claim_statements(json!({ "Email": "alice@kilt.io" }));
+        assert!(verify_claim_hashes(&statements, &nonce_map(), &claim_hashes()).is_ok());
+
+        // a disclosed subset of the properties is still covered
+        let owner_only = email_claim_statements(json!({}));
+        assert!(verify_claim_hashes(&owner_only, &nonce_map(), &claim_hashes()).is_ok());
+    }
+
+    #[test]
+    fn rejects_changed_claims() {
+        let changed = email_claim_statements(json!({ "Email": "mallory@kilt.io" }));
+        assert!(matches!(
+            verify_claim_hashes(&changed, &nonce_map(), &claim_hashes()),
+            Err(CredentialIntegrityError::MissingNonce(_))
+        ));
+
+        let statements = email_claim_statements(json!({ "Email": "alice@kilt.io" }));
+        let mut wrong_nonce = nonce_map();
+        wrong_nonce.insert(EMAIL_STATEMENT_HASH.to_string(), OWNER_NONCE.to_string());
+        assert!(matches!(
+            verify_claim_hashes(&statements, &wrong_nonce, &claim_hashes()),
+            Err(CredentialIntegrityError::ClaimHash(hash)) if hash == EMAIL_STATEMENT_HASH
+        ));
+    }
+
+    #[test]
+    fn rejects_contents_which_are_not_an_object() {
+        let owner = DidUri::from_str(OWNER).unwrap();
+        assert!(matches!(
+            claim_statements(EMAIL_CTYPE_HASH, &owner, &json!(["alice@kilt.io"])),
+            Err(CredentialIntegrityError::Contents)
+        ));
+    }
+
+    #[test]
+    fn calculates_the_root_hash() {
+        assert_eq!(
+            calculate_root_hash(&claim_hashes(), &[], None).unwrap(),
+            ROOT_HASH
+        );
+        let delegation_id = format!("0x{}", "11".repeat(32));
+        assert_eq!(
+            calculate_root_hash(&claim_hashes(), &[], Some(&delegation_id)).unwrap(),
+            "0x5870603a761f6cdeadbb0722fe5a6131a09dd6f382b6ae56343013a17ac27f96"
+        );
+        assert!(matches!(
+            calculate_root_hash(&["0xzz".to_string()], &[], None),
+            Err(CredentialIntegrityError::Hex(_))
+        ));
+    }
+
+    #[test]
+    fn builds_the_presentation_signing_data() {
+        let root_hash = hex::decode(&ROOT_HASH[2..]).unwrap();
+
+        assert_eq!(
+            presentation_signing_data(ROOT_HASH, None).unwrap(),
+            root_hash
+        );
+        assert_eq!(
+            presentation_signing_data(ROOT_HASH, Some("0x0102")).unwrap(),
+            [root_hash.as_slice(), &[1, 2]].concat()
+        );
+        assert_eq!(
+            presentation_signing_data(ROOT_HASH, Some("challenge")).unwrap(),
+            [root_hash.as_slice(), b"challenge"].concat()
+        );
+        assert!(matches!(
+            presentation_signing_data("root hash", None),
+            Err(CredentialIntegrityError::Hex(_))
+        ));
+    }
+}
@@ -0,0 +1,398 @@
+//! CTypes describe the structure of claims with a JSON schema.
+//! A CType is identified by the blake2b-256 hash of its canonical JSON (sorted keys, no whitespace), which only
+//! contains the schema fields listed in [CTYPE_HASH_FIELDS]. Other fields like `$id` or `description` do not change it.
+//! The schema is not stored on chain, only the hash and the block it was registered in.
+
+use serde_json::{Map, Value};
+use sp_core::H256;
+use subxt::{ext::sp_core::blake2_256, OnlineClient};
+
+use crate::kilt::{
+    error::CTypeError,
+    runtime::{self, runtime_types::ctype::pallet::Call},
+    KiltConfig, RuntimeCall,
+};
+
+pub const CTYPE_ID_PREFIX: &str = "kilt:ctype:";
+
+/// Fields of the CType which are hashed and stored on chain, like in the KILT SDK.
+/// `additionalProperties` is only part of the hash if the CType sets it.
+const CTYPE_HASH_FIELDS: [&str; 5] = [
+    "$schema",
+    "additionalProperties",
+    "properties",
+    "title",
+    "type",
+];
+
+/// Returns the canonical JSON of the CType which is hashed and stored in `ctype.add`.
+fn canonical_ctype(ctype: &Value) -> Result<String, CTypeError> {
+    let ctype = ctype
+        .as_object()
+        .ok_or_else(|| CTypeError::Schema("CType must be a JSON object".to_string()))?;
+    let canonical: Map<String, Value> = CTYPE_HASH_FIELDS
+        .iter()
+        .filter_map(|field| Some((field.to_string(), ctype.get(*field)?.clone())))
+        .collect();
+    // the map of serde_json is sorted by key, so the serialization is canonical
+    Ok(serde_json::to_string(&canonical)?)
+}
+
+pub fn ctype_hash(ctype: &Value) -> Result<H256, CTypeError> {
+    Ok(H256::from(blake2_256(canonical_ctype(ctype)?.as_bytes())))
+}
+
+pub fn ctype_id(hash: &H256) -> String {
+    format!("{}{:?}", CTYPE_ID_PREFIX, hash)
+}
+
+/// Returns the CType with its `$id` set to the id derived from its hash.
+pub fn with_ctype_id(ctype: &Value) -> Result<Value, CTypeError> {
+    Ok(set_ctype_id(ctype.clone(), &ctype_hash(ctype)?))
+}
+
+fn set_ctype_id(mut ctype: Value, hash: &H256) -> Value {
+    if let Some(object) = ctype.as_object_mut() {
+        object.insert("$id".to_string(), Value::String(ctype_id(hash)));
+    }
+    ctype
+}
+
+pub fn add_ctype_call(ctype: &Value) -> Result<RuntimeCall, CTypeError> {
+    Ok(RuntimeCall::Ctype(Call::add {
+        ctype: canonical_ctype(ctype)?.into_bytes(),
+    }))
+}
+
+pub async fn is_ctype_on_chain(
+    hash: H256,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<bool, subxt::Error> {
+    Ok(chain_client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&runtime::storage().ctype().ctypes(hash))
+        .await?
+        .is_some())
+}
+
+/// Searches the call for a `ctype.add`, also inside of DID calls and batches.
+fn find_ctype_in_call(call: RuntimeCall) -> Option<Vec<u8>> {
+    match call {
+        RuntimeCall::Ctype(Call::add { ctype }) => Some(ctype),
+        RuntimeCall::Did(runtime::runtime_types::did::pallet::Call::submit_did_call {
+            did_call,
+            ..
+        }) => find_ctype_in_call(did_call.call),
+        RuntimeCall::Utility(
+            runtime::runtime_types::pallet_utility::pallet::Call::batch { calls }
+            | runtime::runtime_types::pallet_utility::pallet::Call::batch_all { calls }
+            | runtime::runtime_types::pallet_utility::pallet::Call::force_batch { calls },
+        ) => calls.into_iter().find_map(find_ctype_in_call),
+        _ => None,
+    }
+}
+
+/// Fetches the CType from the extrinsics of the block it was registered in.
+/// Returns `None` if the CType is not registered.
+pub async fn fetch_ctype_from_chain(
+    hash: H256,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Option<Value>, CTypeError> {
+    let entry = chain_client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&runtime::storage().ctype().ctypes(hash))
+        .await?;
+    let Some(entry) = entry else {
+        return Ok(None);
+    };
+
+    let block_hash = chain_client
+        .rpc()
+        .block_hash(Some(entry.created_at.into()))
+        .await?
+        .ok_or_else(|| CTypeError::NotFound(format!("{:?}", hash)))?;
+    let block = chain_client.blocks().at(block_hash).await?;
+    let extrinsics = block.body().await?.extrinsics();
+
+    for extrinsic in extrinsics.iter() {
+        let Ok(call) = extrinsic?.as_root_extrinsic::<RuntimeCall>() else {
+            continue;
+        };
+        let Some(ctype_bytes) = find_ctype_in_call(call) else {
+            continue;
+        };
+        // the chain stores the hash of the submitted bytes, they are not necessarily canonical
+        if H256::from(blake2_256(&ctype_bytes)) != hash {
+            continue;
+        }
+        let ctype = serde_json::from_slice::<Value>(&ctype_bytes)?;
+        return Ok(Some(set_ctype_id(ctype, &hash)));
+    }
+    Ok(None)
+}
+
+/// Validates the claim contents against the JSON schema of the CType.
+/// Only the subset of JSON schema used by CTypes is supported.
+/// References to other CTypes are not resolved and accepted as they are.
+pub fn validate_claim_contents(ctype: &Value, contents: &Value) -> Result<(), CTypeError> {
+    let mut schema = ctype.clone();
+    // a claim only needs to disclose a subset of the properties
+    if let Some(object) = schema.as_object_mut() {
+        object.remove("required");
+    }
+    validate_value(&schema, contents, "contents")
+}
+
+fn type_matches(type_name: &str, value: &Value) -> bool {
+    match type_name {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+fn validate_format(format: &str, value: &str) -> bool {
+    match format {
+        "date" => chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+        "time" => chrono::NaiveTime::parse_from_str(value, "%H:%M:%S").is_ok(),
+        "uri" => url::Url::parse(value).is_ok(),
+        _ => true,
+    }
+}
+
+fn validate_value(schema: &Value, value: &Value, path: &str) -> Result<(), CTypeError> {
+    let Some(schema) = schema.as_object() else {
+        return Ok(());
+    };
+    let fail = |reason: &str| CTypeError::Validation(format!("{}: {}", path, reason));
+
+    if schema.contains_key("$ref") {
+        return Ok(());
+    }
+
+    match schema.get("type") {
+        Some(Value::String(type_name)) if !type_matches(type_name, value) => {
+            return Err(fail(&format!("expected {}", type_name)));
+        }
+        Some(Value::Array(types))
+            if !types
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|t| type_matches(t, value)) =>
+        {
+            return Err(fail("type is not allowed"));
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            return Err(fail("value is not allowed"));
+        }
+    }
+
+    match value {
+        Value::String(string) => {
+            let length = string.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if length < min {
+                    return Err(fail(&format!("must have at least {} characters", min)));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if length > max {
+                    return Err(fail(&format!("must have at most {} characters", max)));
+                }
+            }
+            if let Some(format) = schema.get("format").and_then(Value::as_str) {
+                if !validate_format(format, string) {
+                    return Err(fail(&format!("is not a valid {}", format)));
+                }
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if number < min {
+                    return Err(fail(&format!("must be at least {}", min)));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if number > max {
+                    return Err(fail(&format!("must be at most {}", max)));
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if (items.len() as u64) < min {
+                    return Err(fail(&format!("must have at least {} items", min)));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if items.len() as u64 > max {
+                    return Err(fail(&format!("must have at most {} items", max)));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_value(item_schema, item, &format!("{}[{}]", path, index))?;
+                }
+            }
+        }
+        Value::Object(object) => validate_object(schema, object, path)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn validate_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+) -> Result<(), CTypeError> {
+    let empty = Map::new();
+    let properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+
+    if let Some(Value::Array(required)) = schema.get("required") {
+        if let Some(missing) = required
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|name| !object.contains_key(*name))
+        {
+            return Err(CTypeError::Validation(format!(
+                "{}: property {} is missing",
+                path, missing
+            )));
+        }
+    }
+
+    let additional_allowed = schema.get("additionalProperties") != Some(&Value::Bool(false));
+    for (name, value) in object {
+        match properties.get(name) {
+            Some(property_schema) => {
+                validate_value(property_schema, value, &format!("{}.{}", path, name))?
+            }
+            None if !additional_allowed => {
+                return Err(CTypeError::Validation(format!(
+                    "{}: property {} is not part of the CType",
+                    path, name
+                )));
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Email CType of the KILT SDK, registered on chain as
+    /// `kilt:ctype:0x3291bb126e33b4862d421bfaa1d2f272e6cdfc4f96658988fbcffea8914bd9ac`.
+    const EMAIL_CTYPE_HASH: &str =
+        "0x3291bb126e33b4862d421bfaa1d2f272e6cdfc4f96658988fbcffea8914bd9ac";
+
+    fn email_ctype() -> Value {
+        json!({
+            "$schema": "http://kilt-protocol.org/draft-01/ctype#",
+            "properties": { "Email": { "type": "string" } },
+            "title": "Email",
+            "type": "object",
+        })
+    }
+
+    #[test]
+    fn hashes_the_email_ctype() {
+        let hash = ctype_hash(&email_ctype()).unwrap();
+        assert_eq!(format!("{:?}", hash), EMAIL_CTYPE_HASH);
+        assert_eq!(ctype_id(&hash), format!("kilt:ctype:{}", EMAIL_CTYPE_HASH));
+    }
+
+    #[test]
+    fn ignores_fields_outside_of_the_schema() {
+        let mut ctype = email_ctype();
+        ctype["$id"] = json!("kilt:ctype:0x00");
+        ctype["description"] = json!("Email address of the claimer");
+
+        let hash = ctype_hash(&ctype).unwrap();
+        assert_eq!(format!("{:?}", hash), EMAIL_CTYPE_HASH);
+    }
+
+    #[test]
+    fn hashes_additional_properties_if_set() {
+        let mut ctype = email_ctype();
+        ctype["additionalProperties"] = json!(false);
+
+        assert_ne!(
+            ctype_hash(&ctype).unwrap(),
+            ctype_hash(&email_ctype()).unwrap()
+        );
+    }
+
+    #[test]
+    fn sets_the_id_from_the_hash() {
+        let ctype = with_ctype_id(&email_ctype()).unwrap();
+        assert_eq!(ctype["$id"], format!("kilt:ctype:{}", EMAIL_CTYPE_HASH));
+        // the id does not change the hash, so setting it again keeps it
+        assert_eq!(with_ctype_id(&ctype).unwrap(), ctype);
+    }
+
+    #[test]
+    fn submits_the_canonical_json() {
+        let RuntimeCall::Ctype(Call::add { ctype }) = add_ctype_call(&email_ctype()).unwrap()
+        else {
+            panic!("expected a ctype.add call");
+        };
+        assert_eq!(
+            String::from_utf8(ctype).unwrap(),
+            r#"{"$schema":"http://kilt-protocol.org/draft-01/ctype#","properties":{"Email":{"type":"string"}},"title":"Email","type":"object"}"#
+        );
+    }
+
+    #[test]
+    fn rejects_a_ctype_which_is_not_an_object() {
+        assert!(matches!(
+            ctype_hash(&json!(["Email"])),
+            Err(CTypeError::Schema(_))
+        ));
+        assert!(matches!(
+            ctype_hash(&json!("Email")),
+            Err(CTypeError::Schema(_))
+        ));
+    }
+
+    #[test]
+    fn validates_claim_contents() {
+        let mut ctype = email_ctype();
+        ctype["required"] = json!(["Email"]);
+
+        assert!(validate_claim_contents(&ctype, &json!({ "Email": "alice@kilt.io" })).is_ok());
+        // a claim may leave out required properties
+        assert!(validate_claim_contents(&ctype, &json!({})).is_ok());
+        assert!(matches!(
+            validate_claim_contents(&ctype, &json!({ "Email": 42 })),
+            Err(CTypeError::Validation(_))
+        ));
+
+        ctype["additionalProperties"] = json!(false);
+        assert!(matches!(
+            validate_claim_contents(&ctype, &json!({ "Phone": "0123" })),
+            Err(CTypeError::Validation(_))
+        ));
+    }
+}
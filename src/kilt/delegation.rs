@@ -0,0 +1,212 @@
+//! Delegation hierarchies of the `delegation` pallet.
+//! The owner of a root node can delegate the permission to attest (or to delegate further) for the CType of the hierarchy to other DIDs.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+use subxt::{
+    ext::sp_runtime::traits::{BlakeTwo256, Hash},
+    utils::AccountId32,
+    OnlineClient,
+};
+
+use crate::kilt::{
+    did_uri::DidUri,
+    error::DelegationError,
+    runtime::{
+        self,
+        runtime_types::{
+            delegation::{delegation_hierarchy::Permissions, pallet::Call},
+            did::did_details::DidSignature,
+        },
+    },
+    KiltConfig, RuntimeCall,
+};
+
+const ATTEST_PERMISSION: u32 = 0b01;
+const DELEGATE_PERMISSION: u32 = 0b10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DelegationPermission {
+    Attest,
+    Delegate,
+}
+
+impl DelegationPermission {
+    fn bit(&self) -> u32 {
+        match self {
+            DelegationPermission::Attest => ATTEST_PERMISSION,
+            DelegationPermission::Delegate => DELEGATE_PERMISSION,
+        }
+    }
+
+    fn to_bits(permissions: &[DelegationPermission]) -> u32 {
+        permissions.iter().fold(0, |bits, p| bits | p.bit())
+    }
+
+    fn from_bits(bits: u32) -> Vec<DelegationPermission> {
+        [DelegationPermission::Attest, DelegationPermission::Delegate]
+            .into_iter()
+            .filter(|p| bits & p.bit() != 0)
+            .collect()
+    }
+}
+
+/// On-chain state of a delegation node, together with the CType of its hierarchy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DelegationNodeDetails {
+    pub id: String,
+    pub hierarchy_root_id: String,
+    pub parent_id: Option<String>,
+    pub ctype_hash: String,
+    pub owner: DidUri,
+    pub permissions: Vec<DelegationPermission>,
+    pub revoked: bool,
+    pub children: Vec<String>,
+    #[serde(skip)]
+    ctype: H256,
+}
+
+impl DelegationNodeDetails {
+    pub fn has_permission(&self, permission: DelegationPermission) -> bool {
+        self.permissions.contains(&permission)
+    }
+}
+
+pub fn parse_delegation_id(delegation_id: &str) -> Result<H256, DelegationError> {
+    let bytes: [u8; 32] = hex::decode(delegation_id.trim_start_matches("0x").trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| DelegationError::Id(delegation_id.to_string()))?;
+    Ok(H256::from(bytes))
+}
+
+pub fn random_delegation_id() -> H256 {
+    H256::from(rand::thread_rng().gen::<[u8; 32]>())
+}
+
+/// Fetches the delegation node. Returns `None` if there is no node with the id on chain.
+pub async fn query_delegation_node(
+    delegation_id: H256,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Option<DelegationNodeDetails>, subxt::Error> {
+    let storage = chain_client.storage().at_latest().await?;
+    let Some(node) = storage
+        .fetch(
+            &runtime::storage()
+                .delegation()
+                .delegation_nodes(delegation_id),
+        )
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let hierarchy = storage
+        .fetch(
+            &runtime::storage()
+                .delegation()
+                .delegation_hierarchies(node.hierarchy_root_id),
+        )
+        .await?
+        .ok_or_else(|| subxt::Error::Other("Delegation hierarchy not found".to_string()))?;
+
+    Ok(Some(DelegationNodeDetails {
+        id: format!("{:?}", delegation_id),
+        hierarchy_root_id: format!("{:?}", node.hierarchy_root_id),
+        parent_id: node.parent.map(|parent| format!("{:?}", parent)),
+        ctype_hash: format!("{:?}", hierarchy.ctype_hash),
+        owner: DidUri::full(node.details.owner),
+        permissions: DelegationPermission::from_bits(node.details.permissions.bits),
+        revoked: node.details.revoked,
+        children: node
+            .children
+            .0
+            .iter()
+            .map(|child| format!("{:?}", child))
+            .collect(),
+        ctype: hierarchy.ctype_hash,
+    }))
+}
+
+/// Checks that `attester` may attest claims of `ctype_hash` with the delegation node.
+pub async fn verify_attestation_delegation(
+    delegation_id: H256,
+    attester: &AccountId32,
+    ctype_hash: H256,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<DelegationNodeDetails, DelegationError> {
+    let node = query_delegation_node(delegation_id, chain_client)
+        .await?
+        .ok_or_else(|| DelegationError::NotFound(format!("{:?}", delegation_id)))?;
+
+    if node.revoked {
+        return Err(DelegationError::Revoked(node.id));
+    }
+    if node.owner != DidUri::full(attester.clone()) {
+        return Err(DelegationError::NotOwner(node.id));
+    }
+    if !node.has_permission(DelegationPermission::Attest) {
+        return Err(DelegationError::MissingPermission(node.id));
+    }
+    if node.ctype != ctype_hash {
+        return Err(DelegationError::CTypeMismatch(node.id));
+    }
+    Ok(node)
+}
+
+/// Hash the delegate has to sign with its authentication key to accept a new delegation node.
+pub fn delegation_creation_hash(
+    delegation_id: H256,
+    hierarchy_root_id: H256,
+    parent_id: H256,
+    permissions: &[DelegationPermission],
+) -> H256 {
+    let mut hashed_values = delegation_id.as_bytes().to_vec();
+    hashed_values.extend_from_slice(hierarchy_root_id.as_bytes());
+    hashed_values.extend_from_slice(parent_id.as_bytes());
+    hashed_values.extend_from_slice(&DelegationPermission::to_bits(permissions).to_le_bytes());
+    BlakeTwo256::hash(&hashed_values)
+}
+
+pub fn create_hierarchy_call(root_node_id: H256, ctype_hash: H256) -> RuntimeCall {
+    RuntimeCall::Delegation(Call::create_hierarchy {
+        root_node_id,
+        ctype_hash,
+    })
+}
+
+pub fn add_delegation_call(
+    delegation_id: H256,
+    parent_id: H256,
+    delegate: AccountId32,
+    permissions: &[DelegationPermission],
+    delegate_signature: DidSignature,
+) -> RuntimeCall {
+    RuntimeCall::Delegation(Call::add_delegation {
+        delegation_id,
+        parent_id,
+        delegate,
+        permissions: Permissions {
+            bits: DelegationPermission::to_bits(permissions),
+        },
+        delegate_signature,
+    })
+}
+
+/// Revokes the node and all of its children. The revoking DID must own the node or one of its parents.
+pub fn revoke_delegation_call(
+    delegation_id: H256,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<RuntimeCall, DelegationError> {
+    let constants = chain_client.constants();
+    let delegation = runtime::constants().delegation();
+
+    Ok(RuntimeCall::Delegation(Call::revoke_delegation {
+        delegation_id,
+        max_parent_checks: constants.at(&delegation.max_parent_checks())?,
+        max_revocations: constants.at(&delegation.max_revocations())?,
+    }))
+}
@@ -0,0 +1,384 @@
+//! Resolution of KILT DIDs into W3C DID Documents.
+//! See <https://www.w3.org/TR/did-core/> for the document and <https://w3c-ccg.github.io/did-resolution/> for the resolution result.
+
+use base58::ToBase58;
+use serde::{Deserialize, Serialize};
+use subxt::{
+    ext::{
+        codec::{Decode, Encode},
+        sp_core::twox_64,
+    },
+    utils::AccountId32,
+    OnlineClient,
+};
+
+use crate::kilt::{
+    did_lookup::query_connected_accounts,
+    did_uri::DidUri,
+    error::{DidError, TxError},
+    light_did::{LightDid, LightDidAuthKeyType},
+    runtime::{
+        self,
+        runtime_types::did::{
+            did_details::{DidDetails, DidEncryptionKey, DidPublicKey, DidVerificationKey},
+            service_endpoints::DidEndpoint,
+        },
+    },
+    web3_name::{query_web3_name, WEB3_NAME_PREFIX},
+    KiltConfig,
+};
+
+const W3C_DID_CONTEXT_URL: &str = "https://www.w3.org/ns/did/v1";
+const KILT_DID_CONTEXT_URL: &str = "ipfs://QmU7QkuTCPz7NmD5bD7Z7mQVz2UsSPaDX18BxPRUmR4j1Y";
+const MAX_SERVICE_ENDPOINTS: u32 = 100;
+
+const SR25519_VERIFICATION_KEY: &str = "Sr25519VerificationKey2020";
+const ED25519_VERIFICATION_KEY: &str = "Ed25519VerificationKey2018";
+const ECDSA_VERIFICATION_KEY: &str = "EcdsaSecp256k1VerificationKey2019";
+const X25519_KEY_AGREEMENT_KEY: &str = "X25519KeyAgreementKey2019";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMethod {
+    pub id: String,
+    pub controller: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub public_key_base58: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Service {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: Vec<String>,
+    pub service_endpoint: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocument {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    pub id: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub also_known_as: Vec<String>,
+    pub verification_method: Vec<VerificationMethod>,
+    pub authentication: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub key_agreement: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub assertion_method: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub capability_delegation: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub service: Vec<Service>,
+    /// Accounts linked to the DID with the `did_lookup` pallet
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub connected_accounts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocumentMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deactivated: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidResolutionMetadata {
+    pub content_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidResolutionResult {
+    pub did_document: Option<DidDocument>,
+    pub did_document_metadata: DidDocumentMetadata,
+    pub did_resolution_metadata: DidResolutionMetadata,
+}
+
+impl DidResolutionResult {
+    fn new(did_document: Option<DidDocument>, did_document_metadata: DidDocumentMetadata) -> Self {
+        DidResolutionResult {
+            did_document,
+            did_document_metadata,
+            did_resolution_metadata: DidResolutionMetadata {
+                content_type: "application/did+json".to_string(),
+            },
+        }
+    }
+}
+
+impl DidDocument {
+    fn new(did: &str) -> Self {
+        DidDocument {
+            context: vec![
+                W3C_DID_CONTEXT_URL.to_string(),
+                KILT_DID_CONTEXT_URL.to_string(),
+            ],
+            id: did.to_string(),
+            also_known_as: vec![],
+            verification_method: vec![],
+            authentication: vec![],
+            key_agreement: vec![],
+            assertion_method: vec![],
+            capability_delegation: vec![],
+            service: vec![],
+            connected_accounts: vec![],
+        }
+    }
+
+    /// Adds the key as verification method and returns its key URI.
+    fn add_verification_method(
+        &mut self,
+        fragment: &str,
+        type_: &str,
+        public_key: &[u8],
+    ) -> String {
+        let id = format!("{}#{}", self.id, fragment);
+        if !self.verification_method.iter().any(|vm| vm.id == id) {
+            self.verification_method.push(VerificationMethod {
+                id: id.clone(),
+                controller: self.id.clone(),
+                type_: type_.to_string(),
+                public_key_base58: public_key.to_base58(),
+            });
+        }
+        id
+    }
+}
+
+fn public_key_type_and_bytes(key: &DidPublicKey<AccountId32>) -> (&'static str, Vec<u8>) {
+    match key {
+        DidPublicKey::PublicVerificationKey(DidVerificationKey::Sr25519(pk)) => {
+            (SR25519_VERIFICATION_KEY, pk.0.to_vec())
+        }
+        DidPublicKey::PublicVerificationKey(DidVerificationKey::Ed25519(pk)) => {
+            (ED25519_VERIFICATION_KEY, pk.0.to_vec())
+        }
+        DidPublicKey::PublicVerificationKey(DidVerificationKey::Ecdsa(pk)) => {
+            (ECDSA_VERIFICATION_KEY, pk.0.to_vec())
+        }
+        DidPublicKey::PublicVerificationKey(DidVerificationKey::Account(account_id)) => {
+            (SR25519_VERIFICATION_KEY, account_id.0.to_vec())
+        }
+        DidPublicKey::PublicEncryptionKey(DidEncryptionKey::X25519(pk)) => {
+            (X25519_KEY_AGREEMENT_KEY, pk.to_vec())
+        }
+    }
+}
+
+fn bytes_to_string(bytes: Vec<u8>) -> String {
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// Fetches all service endpoints of the full DID.
+pub async fn get_did_service_endpoints(
+    did: &AccountId32,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Vec<DidEndpoint>, subxt::Error> {
+    let storage = chain_client.storage().at_latest().await?;
+
+    // The service endpoints are stored in a double map, hashed with `Twox64Concat` for the DID.
+    let encoded_did = did.encode();
+    let mut prefix = runtime::storage()
+        .did()
+        .service_endpoints_root()
+        .to_root_bytes();
+    prefix.extend(twox_64(&encoded_did));
+    prefix.extend(encoded_did);
+
+    let keys = storage
+        .fetch_keys(&prefix, MAX_SERVICE_ENDPOINTS, None)
+        .await?;
+
+    let mut endpoints = Vec::with_capacity(keys.len());
+    for key in keys {
+        if let Some(bytes) = storage.fetch_raw(&key.0).await? {
+            endpoints.push(DidEndpoint::decode(&mut &bytes[..])?);
+        }
+    }
+    Ok(endpoints)
+}
+
+fn full_did_to_document(
+    did: &str,
+    details: DidDetails,
+    endpoints: Vec<DidEndpoint>,
+) -> DidDocument {
+    let did = did.to_string();
+    let mut document = DidDocument::new(&did);
+
+    for (key_id, key_details) in details.public_keys.0.iter() {
+        let fragment = format!("{:?}", key_id);
+        let (type_, public_key) = public_key_type_and_bytes(&key_details.key);
+        let key_uri = document.add_verification_method(&fragment, type_, &public_key);
+
+        if *key_id == details.authentication_key {
+            document.authentication.push(key_uri.clone());
+        }
+        if details.key_agreement_keys.0.contains(key_id) {
+            document.key_agreement.push(key_uri.clone());
+        }
+        if details.attestation_key == Some(*key_id) {
+            document.assertion_method.push(key_uri.clone());
+        }
+        if details.delegation_key == Some(*key_id) {
+            document.capability_delegation.push(key_uri);
+        }
+    }
+
+    document.service = endpoints
+        .into_iter()
+        .map(|endpoint| Service {
+            id: format!("{}#{}", did, bytes_to_string(endpoint.id.0)),
+            type_: endpoint
+                .service_types
+                .0
+                .into_iter()
+                .map(|t| bytes_to_string(t.0))
+                .collect(),
+            service_endpoint: endpoint
+                .urls
+                .0
+                .into_iter()
+                .map(|u| bytes_to_string(u.0))
+                .collect(),
+        })
+        .collect();
+
+    document
+}
+
+async fn resolve_full_did(
+    did: &DidUri,
+    account_id: &AccountId32,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<DidResolutionResult, TxError> {
+    let storage = chain_client.storage().at_latest().await?;
+    let details = storage
+        .fetch(&runtime::storage().did().did(account_id))
+        .await?;
+
+    let Some(details) = details else {
+        let is_deleted = storage
+            .fetch(&runtime::storage().did().did_blacklist(account_id))
+            .await?
+            .is_some();
+
+        if is_deleted {
+            return Ok(DidResolutionResult::new(
+                None,
+                DidDocumentMetadata {
+                    deactivated: Some(true),
+                    ..Default::default()
+                },
+            ));
+        }
+        return Err(TxError::Did(DidError::NotFound(did.to_string())));
+    };
+
+    let endpoints = get_did_service_endpoints(account_id, chain_client).await?;
+    let mut document = full_did_to_document(&did.to_string(), details, endpoints);
+    if let Some(web3_name) = query_web3_name(account_id, chain_client).await? {
+        document
+            .also_known_as
+            .push(format!("{}{}", WEB3_NAME_PREFIX, web3_name));
+    }
+    document.connected_accounts = query_connected_accounts(account_id, chain_client).await?;
+
+    Ok(DidResolutionResult::new(
+        Some(document),
+        DidDocumentMetadata::default(),
+    ))
+}
+
+async fn resolve_light_did(
+    did: &DidUri,
+    light_did: &LightDid,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<DidResolutionResult, TxError> {
+    // A light DID is replaced by the full DID once it is upgraded.
+    let full_did = did.to_full();
+    let full_did_account = full_did.full_account_id().map_err(DidError::from)?;
+    let storage = chain_client.storage().at_latest().await?;
+
+    if storage
+        .fetch(&runtime::storage().did().did(full_did_account))
+        .await?
+        .is_some()
+    {
+        return Ok(DidResolutionResult::new(
+            None,
+            DidDocumentMetadata {
+                canonical_id: Some(full_did.to_string()),
+                ..Default::default()
+            },
+        ));
+    }
+
+    if storage
+        .fetch(&runtime::storage().did().did_blacklist(full_did_account))
+        .await?
+        .is_some()
+    {
+        return Ok(DidResolutionResult::new(
+            None,
+            DidDocumentMetadata {
+                deactivated: Some(true),
+                ..Default::default()
+            },
+        ));
+    }
+
+    let did = did.to_string();
+    let mut document = DidDocument::new(&did);
+
+    let auth_key_type = match light_did.auth_key_type {
+        LightDidAuthKeyType::Sr25519 => SR25519_VERIFICATION_KEY,
+        LightDidAuthKeyType::Ed25519 => ED25519_VERIFICATION_KEY,
+    };
+    let auth_key_uri =
+        document.add_verification_method("authentication", auth_key_type, &light_did.auth_key);
+    document.authentication.push(auth_key_uri);
+
+    if let Some(encryption_key) = light_did.encryption_key {
+        let encryption_key_uri = document.add_verification_method(
+            "encryption",
+            X25519_KEY_AGREEMENT_KEY,
+            &encryption_key,
+        );
+        document.key_agreement.push(encryption_key_uri);
+    }
+
+    document.service = light_did
+        .services
+        .iter()
+        .map(|service| Service {
+            id: format!("{}#{}", did, service.id),
+            type_: service.types.clone(),
+            service_endpoint: service.urls.clone(),
+        })
+        .collect();
+
+    Ok(DidResolutionResult::new(
+        Some(document),
+        DidDocumentMetadata::default(),
+    ))
+}
+
+/// Resolves a full or light KILT DID into a W3C DID Document.
+pub async fn resolve_did(
+    did: &DidUri,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<DidResolutionResult, TxError> {
+    match did {
+        DidUri::Full(account_id) => resolve_full_did(did, account_id, chain_client).await,
+        DidUri::Light { light_did, .. } => resolve_light_did(did, light_did, chain_client).await,
+    }
+}
@@ -0,0 +1,138 @@
+//! Linking of accounts to DIDs with the `did_lookup` pallet.
+//! A linked account proves that it belongs to the DID, e.g. the payment account of the device.
+
+use serde::{Deserialize, Serialize};
+use subxt::{
+    ext::{
+        codec::{Decode, Encode},
+        sp_core::{blake2_128, sr25519, Pair},
+    },
+    tx::PairSigner,
+    utils::AccountId32,
+    OnlineClient,
+};
+
+use crate::kilt::{
+    did_helper::format_ss58_address,
+    did_uri::DidUri,
+    error::DidLookupError,
+    runtime::{
+        self,
+        runtime_types::{
+            pallet_did_lookup::{
+                associate_account_request::AssociateAccountRequest,
+                linkable_account::LinkableAccountId, pallet::Call,
+            },
+            sp_core::sr25519::Signature,
+            sp_runtime::MultiSignature,
+        },
+    },
+    utils::get_current_block,
+    KiltConfig, RuntimeCall,
+};
+
+/// Number of blocks the account signature is valid for.
+const ASSOCIATION_VALIDITY_BLOCKS: u64 = 50;
+/// Number of storage keys fetched per request when listing the connected accounts.
+const CONNECTED_ACCOUNTS_PAGE_SIZE: u32 = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidConnections {
+    pub did: DidUri,
+    pub accounts: Vec<String>,
+}
+
+fn format_linkable_account(account: &LinkableAccountId) -> String {
+    match account {
+        LinkableAccountId::AccountId20(account) => format!("0x{}", hex::encode(account.0)),
+        LinkableAccountId::AccountId32(account) => format_ss58_address(account),
+    }
+}
+
+/// Returns the DID the account is linked to.
+pub async fn query_connected_did(
+    account: &AccountId32,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Option<DidUri>, subxt::Error> {
+    let account = LinkableAccountId::AccountId32(account.clone());
+    let connection = chain_client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&runtime::storage().did_lookup().connected_dids(&account))
+        .await?;
+
+    Ok(connection.map(|record| DidUri::full(record.did)))
+}
+
+/// Returns all accounts linked to the DID. The keys are fetched page by page, a DID can link any number of accounts.
+pub async fn query_connected_accounts(
+    did: &AccountId32,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Vec<String>, DidLookupError> {
+    let storage = chain_client.storage().at_latest().await?;
+
+    // The connected accounts are stored in a double map, hashed with `Blake2_128Concat` for the DID and the account.
+    let encoded_did = did.encode();
+    let mut prefix = runtime::storage()
+        .did_lookup()
+        .connected_accounts_root()
+        .to_root_bytes();
+    prefix.extend(blake2_128(&encoded_did));
+    prefix.extend(encoded_did);
+
+    let mut accounts = vec![];
+    let mut start_key: Option<Vec<u8>> = None;
+    loop {
+        let keys = storage
+            .fetch_keys(&prefix, CONNECTED_ACCOUNTS_PAGE_SIZE, start_key.as_deref())
+            .await?;
+
+        for key in &keys {
+            // skip the hash of the account, the encoded account follows
+            let mut encoded_account = key
+                .0
+                .get(prefix.len() + 16..)
+                .ok_or_else(|| DidLookupError::StorageKey(format!("0x{}", hex::encode(&key.0))))?;
+            let account =
+                LinkableAccountId::decode(&mut encoded_account).map_err(subxt::Error::from)?;
+            accounts.push(format_linkable_account(&account));
+        }
+
+        if keys.len() < CONNECTED_ACCOUNTS_PAGE_SIZE as usize {
+            return Ok(accounts);
+        }
+        start_key = keys.last().map(|key| key.0.clone());
+    }
+}
+
+/// Creates the call linking the account of `account_signer` to the DID.
+/// The account signs the DID and an expiration block, so the call can be submitted with the DID as origin.
+pub async fn associate_account_call(
+    did: &AccountId32,
+    account_signer: &PairSigner<KiltConfig, sr25519::Pair>,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<RuntimeCall, subxt::Error> {
+    let expiration = get_current_block(chain_client).await? + ASSOCIATION_VALIDITY_BLOCKS;
+
+    // The pallet expects the signed payload to be wrapped like polkadot-js `signRaw` does.
+    let payload = (did, expiration).encode();
+    let wrapped_payload = [b"<Bytes>".as_slice(), &payload, b"</Bytes>"].concat();
+    let signature = account_signer.signer().sign(&wrapped_payload);
+
+    let account: AccountId32 = account_signer.account_id().clone().into();
+    Ok(RuntimeCall::DidLookup(Call::associate_account {
+        req: AssociateAccountRequest::Polkadot(
+            account,
+            MultiSignature::Sr25519(Signature(signature.into())),
+        ),
+        expiration,
+    }))
+}
+
+pub fn remove_account_association_call(account: &AccountId32) -> RuntimeCall {
+    RuntimeCall::DidLookup(Call::remove_account_association {
+        account: LinkableAccountId::AccountId32(account.clone()),
+    })
+}
@@ -0,0 +1,128 @@
+//! Verification of signatures created with the authentication key of a DID.
+//! Wallets based on the polkadot extension wrap the signed data in `<Bytes>` tags, so both forms are accepted.
+
+use subxt::{
+    ext::sp_core::{ecdsa, ed25519, sr25519, Pair},
+    OnlineClient,
+};
+
+use crate::kilt::{
+    did_uri::DidKeyUri,
+    error::SignatureError,
+    light_did::LightDidAuthKeyType,
+    runtime::{
+        self,
+        runtime_types::did::did_details::{DidPublicKey, DidVerificationKey},
+    },
+    KiltConfig,
+};
+
+const LIGHT_DID_AUTHENTICATION_FRAGMENT: &str = "authentication";
+
+#[derive(Debug, Clone)]
+pub enum VerificationKey {
+    Sr25519([u8; 32]),
+    Ed25519([u8; 32]),
+    Ecdsa([u8; 33]),
+}
+
+impl VerificationKey {
+    fn verify_raw(&self, data: &[u8], signature: &[u8]) -> bool {
+        match self {
+            VerificationKey::Sr25519(key) => sr25519::Signature::from_slice(signature)
+                .map(|signature| {
+                    sr25519::Pair::verify(&signature, data, &sr25519::Public::from_raw(*key))
+                })
+                .unwrap_or_default(),
+            VerificationKey::Ed25519(key) => ed25519::Signature::from_slice(signature)
+                .map(|signature| {
+                    ed25519::Pair::verify(&signature, data, &ed25519::Public::from_raw(*key))
+                })
+                .unwrap_or_default(),
+            VerificationKey::Ecdsa(key) => ecdsa::Signature::from_slice(signature)
+                .map(|signature| {
+                    ecdsa::Pair::verify(&signature, data, &ecdsa::Public::from_raw(*key))
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Verifies the signature over the data, with or without the `<Bytes>` wrapping.
+    pub fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+        let wrapped = [b"<Bytes>".as_slice(), data, b"</Bytes>".as_slice()].concat();
+        self.verify_raw(data, signature) || self.verify_raw(&wrapped, signature)
+    }
+}
+
+/// Resolves the key referenced by the key URI. Fails if it is not the authentication key of the DID.
+pub async fn resolve_authentication_key(
+    key_uri: &DidKeyUri,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<VerificationKey, SignatureError> {
+    if let Some(light_did) = key_uri.did().as_light() {
+        if key_uri.fragment() != LIGHT_DID_AUTHENTICATION_FRAGMENT {
+            return Err(SignatureError::NotAuthenticationKey(key_uri.to_string()));
+        }
+        return Ok(match light_did.auth_key_type {
+            LightDidAuthKeyType::Sr25519 => VerificationKey::Sr25519(light_did.auth_key),
+            LightDidAuthKeyType::Ed25519 => VerificationKey::Ed25519(light_did.auth_key),
+        });
+    }
+
+    let key_id = key_uri.key_id()?;
+    let details = chain_client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(
+            &runtime::storage()
+                .did()
+                .did(key_uri.did().full_account_id()?),
+        )
+        .await?
+        .ok_or_else(|| SignatureError::DidNotFound(key_uri.did().to_string()))?;
+
+    if details.authentication_key != key_id {
+        return Err(SignatureError::NotAuthenticationKey(key_uri.to_string()));
+    }
+    let (_, key) = details
+        .public_keys
+        .0
+        .iter()
+        .find(|(id, _)| *id == key_id)
+        .ok_or_else(|| SignatureError::NotAuthenticationKey(key_uri.to_string()))?;
+
+    match &key.key {
+        DidPublicKey::PublicVerificationKey(DidVerificationKey::Sr25519(key)) => {
+            Ok(VerificationKey::Sr25519(key.0))
+        }
+        DidPublicKey::PublicVerificationKey(DidVerificationKey::Ed25519(key)) => {
+            Ok(VerificationKey::Ed25519(key.0))
+        }
+        DidPublicKey::PublicVerificationKey(DidVerificationKey::Ecdsa(key)) => {
+            Ok(VerificationKey::Ecdsa(key.0))
+        }
+        DidPublicKey::PublicVerificationKey(DidVerificationKey::Account(account_id)) => {
+            Ok(VerificationKey::Sr25519(account_id.0))
+        }
+        DidPublicKey::PublicEncryptionKey(_) => {
+            Err(SignatureError::NotAuthenticationKey(key_uri.to_string()))
+        }
+    }
+}
+
+/// Checks that the hex encoded signature over the data was created with the authentication key of the key URI.
+pub async fn verify_did_signature(
+    key_uri: &DidKeyUri,
+    data: &[u8],
+    signature: &str,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<(), SignatureError> {
+    let signature = hex::decode(signature.trim_start_matches("0x"))
+        .map_err(|_| SignatureError::Format(signature.to_string()))?;
+    let key = resolve_authentication_key(key_uri, chain_client).await?;
+    if !key.verify(data, &signature) {
+        return Err(SignatureError::Invalid(key_uri.to_string()));
+    }
+    Ok(())
+}
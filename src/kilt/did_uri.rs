@@ -0,0 +1,309 @@
+//! Typed DID URIs and DID key URIs.
+//! A full DID has the form `did:kilt:<ss58 address>`, a light DID `did:kilt:light:<auth key type><ss58 address>[:<details>]`.
+//! A key URI is a DID followed by `#` and the key fragment, e.g. `did:kilt:<ss58 address>#0x<key id>`.
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sp_core::H256;
+use std::{fmt, str::FromStr};
+use subxt::{
+    ext::sp_core::crypto::{AccountId32 as SpAccountId32, Ss58AddressFormat, Ss58Codec},
+    utils::AccountId32,
+};
+
+use crate::kilt::{
+    did_helper::{format_ss58_address, ADDRESS_FORMAT, DID_PREFIX},
+    error::{DidUriError, LightDidError},
+    light_did::{LightDid, LIGHT_DID_PREFIX},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DidUri {
+    Full(AccountId32),
+    Light {
+        /// The URI as it was parsed. Re-encoding the details is not guaranteed to result in the same URI.
+        uri: String,
+        light_did: Box<LightDid>,
+    },
+}
+
+impl DidUri {
+    pub fn full(account_id: impl Into<AccountId32>) -> Self {
+        DidUri::Full(account_id.into())
+    }
+
+    pub fn light(light_did: LightDid) -> Result<Self, LightDidError> {
+        Ok(DidUri::Light {
+            uri: light_did.encode()?,
+            light_did: Box::new(light_did),
+        })
+    }
+
+    pub fn is_light(&self) -> bool {
+        matches!(self, DidUri::Light { .. })
+    }
+
+    /// Returns the account id of a full DID. Light DIDs are not stored on chain and return an error.
+    pub fn full_account_id(&self) -> Result<&AccountId32, DidUriError> {
+        match self {
+            DidUri::Full(account_id) => Ok(account_id),
+            DidUri::Light { .. } => Err(DidUriError::NotFullDid(self.to_string())),
+        }
+    }
+
+    pub fn as_light(&self) -> Option<&LightDid> {
+        match self {
+            DidUri::Full(_) => None,
+            DidUri::Light { light_did, .. } => Some(light_did),
+        }
+    }
+
+    /// Returns the full DID with the same authentication key. For full DIDs this is the DID itself.
+    pub fn to_full(&self) -> DidUri {
+        match self {
+            DidUri::Full(_) => self.clone(),
+            DidUri::Light { light_did, .. } => DidUri::full(light_did.auth_key),
+        }
+    }
+
+    pub fn key_uri(&self, fragment: &str) -> DidKeyUri {
+        DidKeyUri {
+            did: self.clone(),
+            fragment: fragment.to_string(),
+        }
+    }
+}
+
+impl FromStr for DidUri {
+    type Err = DidUriError;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        if uri.contains('#') {
+            return Err(DidUriError::UnexpectedFragment(uri.to_string()));
+        }
+
+        if uri.starts_with(LIGHT_DID_PREFIX) {
+            let light_did = LightDid::from_str(uri)?;
+            return Ok(DidUri::Light {
+                uri: uri.to_string(),
+                light_did: Box::new(light_did),
+            });
+        }
+
+        let address = uri
+            .strip_prefix(DID_PREFIX)
+            .ok_or_else(|| DidUriError::Prefix(uri.to_string()))?;
+
+        let (account_id, format) = SpAccountId32::from_ss58check_with_version(address)
+            .map_err(|_| DidUriError::Address(uri.to_string()))?;
+        if format != Ss58AddressFormat::custom(ADDRESS_FORMAT) {
+            return Err(DidUriError::Address(uri.to_string()));
+        }
+
+        Ok(DidUri::full(<[u8; 32]>::from(account_id)))
+    }
+}
+
+impl fmt::Display for DidUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DidUri::Full(account_id) => {
+                write!(f, "{}{}", DID_PREFIX, format_ss58_address(account_id))
+            }
+            DidUri::Light { uri, .. } => f.write_str(uri),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DidKeyUri {
+    did: DidUri,
+    fragment: String,
+}
+
+impl DidKeyUri {
+    pub fn did(&self) -> &DidUri {
+        &self.did
+    }
+
+    pub fn fragment(&self) -> &str {
+        &self.fragment
+    }
+
+    /// Returns the key id of a full DID key URI.
+    pub fn key_id(&self) -> Result<H256, DidUriError> {
+        parse_key_id(&self.fragment).ok_or_else(|| DidUriError::KeyId(self.to_string()))
+    }
+}
+
+fn parse_key_id(fragment: &str) -> Option<H256> {
+    let bytes: [u8; 32] = hex::decode(fragment.strip_prefix("0x")?)
+        .ok()?
+        .try_into()
+        .ok()?;
+    Some(H256::from(bytes))
+}
+
+impl FromStr for DidKeyUri {
+    type Err = DidUriError;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let (did, fragment) = uri
+            .split_once('#')
+            .ok_or_else(|| DidUriError::MissingFragment(uri.to_string()))?;
+
+        if fragment.is_empty() {
+            return Err(DidUriError::MissingFragment(uri.to_string()));
+        }
+
+        let did = DidUri::from_str(did)?;
+
+        // keys of full DIDs are referenced by their on-chain key id
+        if !did.is_light() && parse_key_id(fragment).is_none() {
+            return Err(DidUriError::KeyId(uri.to_string()));
+        }
+
+        Ok(DidKeyUri {
+            did,
+            fragment: fragment.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for DidKeyUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.did, self.fragment)
+    }
+}
+
+macro_rules! impl_string_serde {
+    ($type:ty) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let uri = String::deserialize(deserializer)?;
+                <$type>::from_str(&uri).map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+impl_string_serde!(DidUri);
+impl_string_serde!(DidKeyUri);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE_ADDRESS: &str = "4siJtc4dYq2gPre8Xj6KJcSjVAdi1gmjctUzjf3AwrtNnhvy";
+    /// Alice in the Polkadot format 0
+    const ALICE_POLKADOT_ADDRESS: &str = "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5";
+    const KEY_ID: &str = "0x8f3a8b1c2d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8";
+    /// Alice with an encryption key and one service, see the tests of the light DID.
+    const ALICE_LIGHT_WITH_DETAILS: &str = "did:kilt:light:004siJtc4dYq2gPre8Xj6KJcSjVAdi1gmjctUzjf3AwrtNnhvy:z14eMxMS7xSK8fMxpGveso3k6HepvoK5CqBJ9xBJoAabpcNf6cmWuH7XAZ8jB1RKxX2KgkZ7AGcYTiVEXAUV6eCAJgamMfKjFmDs847LS1XeYhDwGGkjtniHpSsoA7kT6yG4Hc2vZAesaFBL5ecky53zVCz2jhZradJGmF7tAWckHmoPLVCxnkhANWZyCvFv4Uzvx12ZyrutrBg6h11W1VRNbgWPqFMw3q4h8QMx";
+
+    fn alice_full() -> String {
+        format!("did:kilt:{}", ALICE_ADDRESS)
+    }
+
+    #[test]
+    fn parses_and_displays_a_full_did() {
+        let did = DidUri::from_str(&alice_full()).unwrap();
+        assert!(!did.is_light());
+        assert_eq!(did.to_string(), alice_full());
+        assert_eq!(
+            did.full_account_id().unwrap().to_string(),
+            SpAccountId32::from_ss58check(ALICE_ADDRESS)
+                .unwrap()
+                .to_ss58check()
+        );
+        assert_eq!(did.to_full(), did);
+    }
+
+    #[test]
+    fn keeps_the_uri_of_a_light_did() {
+        let did = DidUri::from_str(ALICE_LIGHT_WITH_DETAILS).unwrap();
+        assert!(did.is_light());
+        assert_eq!(did.to_string(), ALICE_LIGHT_WITH_DETAILS);
+        assert!(did.as_light().unwrap().encryption_key.is_some());
+        assert!(matches!(
+            did.full_account_id(),
+            Err(DidUriError::NotFullDid(_))
+        ));
+        assert_eq!(did.to_full().to_string(), alice_full());
+    }
+
+    #[test]
+    fn parses_key_uris() {
+        let full_key_uri = format!("{}#{}", alice_full(), KEY_ID);
+        let key_uri = DidKeyUri::from_str(&full_key_uri).unwrap();
+        assert_eq!(key_uri.to_string(), full_key_uri);
+        assert_eq!(format!("{:?}", key_uri.key_id().unwrap()), KEY_ID);
+
+        let light_key_uri = format!("{}#encryption", ALICE_LIGHT_WITH_DETAILS);
+        let key_uri = DidKeyUri::from_str(&light_key_uri).unwrap();
+        assert_eq!(key_uri.fragment(), "encryption");
+        assert_eq!(key_uri.did().to_string(), ALICE_LIGHT_WITH_DETAILS);
+        assert!(matches!(key_uri.key_id(), Err(DidUriError::KeyId(_))));
+    }
+
+    #[test]
+    fn round_trips_through_serde() {
+        let key_uri = DidKeyUri::from_str(&format!("{}#{}", alice_full(), KEY_ID)).unwrap();
+        let json = serde_json::to_string(&key_uri).unwrap();
+        assert_eq!(json, format!("\"{}#{}\"", alice_full(), KEY_ID));
+        assert_eq!(serde_json::from_str::<DidKeyUri>(&json).unwrap(), key_uri);
+
+        let did = DidUri::from_str(ALICE_LIGHT_WITH_DETAILS).unwrap();
+        let json = serde_json::to_string(&did).unwrap();
+        assert_eq!(serde_json::from_str::<DidUri>(&json).unwrap(), did);
+    }
+
+    #[test]
+    fn rejects_malformed_dids() {
+        assert!(matches!(
+            DidUri::from_str(&format!("{}#{}", alice_full(), KEY_ID)),
+            Err(DidUriError::UnexpectedFragment(_))
+        ));
+        assert!(matches!(
+            DidUri::from_str(&format!("did:web:{}", ALICE_ADDRESS)),
+            Err(DidUriError::Prefix(_))
+        ));
+        assert!(matches!(
+            DidUri::from_str(&format!("did:kilt:{}", ALICE_POLKADOT_ADDRESS)),
+            Err(DidUriError::Address(_))
+        ));
+        assert!(matches!(
+            DidUri::from_str("did:kilt:4siJtc4dYq2gPre8Xj6KJcSjVAdi1gmjctUzjf3Awrt"),
+            Err(DidUriError::Address(_))
+        ));
+        assert!(matches!(
+            DidUri::from_str("did:kilt:light:02"),
+            Err(DidUriError::LightDid(_))
+        ));
+        assert!(serde_json::from_str::<DidUri>("\"did:kilt:\"").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_key_uris() {
+        assert!(matches!(
+            DidKeyUri::from_str(&alice_full()),
+            Err(DidUriError::MissingFragment(_))
+        ));
+        assert!(matches!(
+            DidKeyUri::from_str(&format!("{}#", alice_full())),
+            Err(DidUriError::MissingFragment(_))
+        ));
+        // keys of full DIDs are referenced by their 32 byte key id
+        for fragment in ["encryption", "0x1234", &KEY_ID[2..]] {
+            assert!(matches!(
+                DidKeyUri::from_str(&format!("{}#{}", alice_full(), fragment)),
+                Err(DidUriError::KeyId(_))
+            ));
+        }
+    }
+}
@@ -5,21 +5,278 @@ use std::string::FromUtf8Error;
 #[derive(thiserror::Error, Debug)]
 pub enum TxError {
     #[error("Subxt error: {0}")]
-    Subxt(#[from] subxt::Error),
+    Subxt(Box<subxt::Error>),
     #[error("Format error: {0}")]
     Format(FormatError),
     #[error("Hex error: {0}")]
     Hex(#[from] hex::FromHexError),
     #[error("DID error: {0}")]
-    Did(DidError),
+    Did(#[from] DidError),
+    #[error("Decode error: {0}")]
+    Decode(String),
+    #[error("Pending call not found: {0}")]
+    PendingCallNotFound(String),
+    #[error("Too many calls are waiting for approval")]
+    PendingCallQueueFull,
+    #[error("Attestation not found: {0}")]
+    AttestationNotFound(String),
+    #[error("Attestation review not found: {0}")]
+    AttestationReviewNotFound(String),
+    #[error("Attestation review is already being approved or rejected: {0}")]
+    AttestationReviewInProgress(String),
+    #[error("Service endpoint error: {0}")]
+    ServiceEndpoint(#[from] ServiceEndpointError),
+    #[error("Web3 name error: {0}")]
+    Web3Name(#[from] Web3NameError),
+    #[error("Account link error: {0}")]
+    DidLookup(#[from] DidLookupError),
+    #[error("Delegation error: {0}")]
+    Delegation(#[from] DelegationError),
+    #[error("CType error: {0}")]
+    CType(#[from] CTypeError),
+}
+
+impl From<subxt::Error> for TxError {
+    fn from(e: subxt::Error) -> Self {
+        TxError::Subxt(Box::new(e))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CredentialIntegrityError {
+    #[error("Invalid hex value: {0}")]
+    Hex(String),
+    #[error("Claim contents must be a JSON object")]
+    Contents,
+    #[error("No nonce for the claim statement: {0}")]
+    MissingNonce(String),
+    #[error("Claim statement is not part of the claim hashes: {0}")]
+    ClaimHash(String),
+    #[error("Root hash does not match the credential: {0}")]
+    RootHash(String),
+    #[error("Claim owner {owner} is not the sender {sender}")]
+    OwnerMismatch { owner: String, sender: String },
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SignatureError {
+    #[error("Invalid signature format: {0}")]
+    Format(String),
+    #[error("Signature does not match the key {0}")]
+    Invalid(String),
+    #[error("{0} is not an authentication key")]
+    NotAuthenticationKey(String),
+    #[error("DID not found: {0}")]
+    DidNotFound(String),
+    #[error("DID URI error: {0}")]
+    DidUri(#[from] DidUriError),
+    #[error("Subxt error: {0}")]
+    Subxt(Box<subxt::Error>),
+}
+
+impl From<subxt::Error> for SignatureError {
+    fn from(e: subxt::Error) -> Self {
+        SignatureError::Subxt(Box::new(e))
+    }
+}
+
+/// Reasons why a credential presentation is not accepted by the verifier.
+#[derive(thiserror::Error, Debug)]
+pub enum PresentationError {
+    #[error("CType was not requested: {0}")]
+    CTypeNotRequested(String),
+    #[error("Credential integrity error: {0}")]
+    Integrity(#[from] CredentialIntegrityError),
+    #[error("Signature key {key_uri} does not belong to the claim owner {owner}")]
+    SignerMismatch { key_uri: String, owner: String },
+    #[error("Presentation is not signed over the requested challenge")]
+    Challenge,
+    #[error("Signature error: {0}")]
+    Signature(#[from] SignatureError),
+    #[error("Credential is not attested: {0}")]
+    NotAttested(String),
+    #[error("Credential is revoked: {0}")]
+    Revoked(String),
+    #[error("Attestation is for another CType: {0}")]
+    CTypeMismatch(String),
+    #[error("Attester is not trusted: {0}")]
+    UntrustedAttester(String),
+    #[error("Subxt error: {0}")]
+    Subxt(Box<subxt::Error>),
+}
+
+impl From<subxt::Error> for PresentationError {
+    fn from(e: subxt::Error) -> Self {
+        PresentationError::Subxt(Box::new(e))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CTypeError {
+    #[error("CType not found: {0}")]
+    NotFound(String),
+    #[error("CType is already registered: {0}")]
+    AlreadyRegistered(String),
+    #[error("Invalid CType: {0}")]
+    Schema(String),
+    #[error("Claim does not match the CType: {0}")]
+    Validation(String),
+    #[error("Invalid CType hash: {0}")]
+    Hash(String),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Subxt error: {0}")]
+    Subxt(Box<subxt::Error>),
+}
+
+impl From<subxt::Error> for CTypeError {
+    fn from(e: subxt::Error) -> Self {
+        CTypeError::Subxt(Box::new(e))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DelegationError {
+    #[error("Delegation node not found: {0}")]
+    NotFound(String),
+    #[error("Delegation node is revoked: {0}")]
+    Revoked(String),
+    #[error("Delegation node is not owned by the attester: {0}")]
+    NotOwner(String),
+    #[error("Delegation node does not allow to attest: {0}")]
+    MissingPermission(String),
+    #[error("Delegation hierarchy is for a different CType: {0}")]
+    CTypeMismatch(String),
+    #[error("Invalid delegation id: {0}")]
+    Id(String),
+    #[error("Invalid delegate signature: {0}")]
+    Signature(String),
+    #[error("Subxt error: {0}")]
+    Subxt(Box<subxt::Error>),
+}
+
+impl From<subxt::Error> for DelegationError {
+    fn from(e: subxt::Error) -> Self {
+        DelegationError::Subxt(Box::new(e))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DidLookupError {
+    #[error("Invalid account address: {0}")]
+    Address(String),
+    #[error("Account is already linked to {0}")]
+    AlreadyLinked(String),
+    #[error("Account is not linked to a DID: {0}")]
+    NotLinked(String),
+    #[error("Malformed storage key of a connected account: {0}")]
+    StorageKey(String),
+    #[error("Subxt error: {0}")]
+    Subxt(Box<subxt::Error>),
+}
+
+impl From<subxt::Error> for DidLookupError {
+    fn from(e: subxt::Error) -> Self {
+        DidLookupError::Subxt(Box::new(e))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Web3NameError {
+    #[error("Web3 name must have between {min} and {max} characters")]
+    Length { min: u32, max: u32 },
+    #[error("Web3 name may only contain lowercase letters, digits, '-' and '_': {0}")]
+    InvalidCharacters(String),
+    #[error("Web3 name is not available: {0}")]
+    Unavailable(String),
+    #[error("DID already owns the web3 name: {0}")]
+    AlreadyClaimed(String),
+    #[error("DID does not own a web3 name")]
+    NotClaimed,
+    #[error("Subxt error: {0}")]
+    Subxt(Box<subxt::Error>),
+}
+
+impl From<subxt::Error> for Web3NameError {
+    fn from(e: subxt::Error) -> Self {
+        Web3NameError::Subxt(Box::new(e))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceEndpointError {
+    #[error("Service id must have between 1 and {0} characters")]
+    IdLength(u32),
+    #[error("Service must have between 1 and {0} types")]
+    TypeCount(u32),
+    #[error("Service type must have between 1 and {0} characters")]
+    TypeLength(u32),
+    #[error("Service must have between 1 and {0} URLs")]
+    UrlCount(u32),
+    #[error("Service URL must have between 1 and {0} characters")]
+    UrlLength(u32),
+    #[error("DID can not have more than {0} service endpoints")]
+    TooManyServices(u32),
+    #[error("Service endpoint not found: {0}")]
+    NotFound(String),
+    #[error("Service endpoint already exists: {0}")]
+    AlreadyExists(String),
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum DidError {
     #[error("DID not found : {0}")]
     NotFound(String),
-    #[error("DID has a wrong format : {0}")]
-    Format(String),
+    #[error("Light DID is invalid : {0}")]
+    LightDid(#[from] LightDidError),
+    #[error("DID URI is invalid : {0}")]
+    Uri(#[from] DidUriError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DidUriError {
+    #[error("missing prefix did:kilt: in {0}")]
+    Prefix(String),
+    #[error("invalid SS58 address in {0}")]
+    Address(String),
+    #[error("unexpected fragment in DID {0}")]
+    UnexpectedFragment(String),
+    #[error("missing key fragment in key URI {0}")]
+    MissingFragment(String),
+    #[error("key id is not a 32 byte hex value in {0}")]
+    KeyId(String),
+    #[error("{0} is not a full DID")]
+    NotFullDid(String),
+    #[error("light DID: {0}")]
+    LightDid(#[from] LightDidError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LightDidError {
+    #[error("missing prefix did:kilt:light:")]
+    Prefix,
+    #[error("too many parts separated by ':'")]
+    TooManyParts,
+    #[error("unknown authentication key type {0}")]
+    UnknownAuthKeyType(String),
+    #[error("invalid SS58 address")]
+    Address,
+    #[error("unexpected SS58 address format {0}")]
+    AddressFormat(u16),
+    #[error("details are not multibase base58 encoded")]
+    MultibasePrefix,
+    #[error("details are not valid base58")]
+    Base58,
+    #[error("details are empty")]
+    EmptyDetails,
+    #[error("unsupported serialization version {0}")]
+    SerializationVersion(u8),
+    #[error("details are not valid CBOR: {0}")]
+    Cbor(String),
+    #[error("unsupported encryption key type {0}")]
+    EncryptionKeyType(String),
+    #[error("encryption key must have 32 bytes, got {0}")]
+    EncryptionKeyLength(usize),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -30,24 +287,60 @@ pub enum FormatError {
     Hex(#[from] FromHexError),
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum MessageError {
+    #[error("Message was already received: {0}")]
+    Replayed(String),
+    #[error("Message is too old: {0}")]
+    Stale(String),
+    #[error("Message is created in the future: {0}")]
+    Future(String),
+    #[error("Message does not reply to a {0} message")]
+    MissingReply(&'static str),
+    #[error("Message replies to an unknown message: {0}")]
+    UnknownReply(String),
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum CredentialAPIError {
     #[error("Challenge error: {0}")]
     Challenge(&'static str),
     #[error("LightDID error: {0}")]
-    LightDID(&'static str),
+    LightDID(#[from] LightDidError),
+    #[error("DID URI error: {0}")]
+    DidUri(#[from] DidUriError),
     #[error("LightDID error: {0}")]
     Did(&'static str),
     #[error("Attestation error: {0}")]
     Attestation(&'static str),
+    #[error("Unexpected message type: {0}")]
+    UnexpectedMessage(&'static str),
+    #[error("Verifier error: {0}")]
+    Verifier(&'static str),
+    #[error("Attestation does not match the credential: {0}")]
+    AttestationMismatch(&'static str),
+    #[error("Credential not found: {0}")]
+    CredentialNotFound(String),
+    #[error("Credential integrity error: {0}")]
+    Integrity(#[from] CredentialIntegrityError),
+    #[error("Message error: {0}")]
+    Message(#[from] MessageError),
     #[error("Subxt error: {0}")]
-    Subxt(#[from] subxt::Error),
+    Subxt(Box<subxt::Error>),
+}
+
+impl From<subxt::Error> for CredentialAPIError {
+    fn from(e: subxt::Error) -> Self {
+        CredentialAPIError::Subxt(Box::new(e))
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum UseCaseAPIError {
     #[error("Use case not found")]
     NotFound,
+    #[error("No attested credential for the use case")]
+    NoCredential,
     #[error("Format Error")]
     Format,
 }
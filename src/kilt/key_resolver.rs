@@ -0,0 +1,118 @@
+//! Resolution of the key agreement key behind the key URI of an encrypted message.
+//! Light DIDs carry the key in the URI, full DIDs are looked up on chain. Lookups of full DIDs are cached
+//! for the block they were read at, so a key change on chain takes effect with the next block.
+
+use sodiumoxide::crypto::box_;
+use sp_core::H256;
+use std::collections::HashMap;
+use subxt::OnlineClient;
+use tokio::sync::Mutex;
+
+use crate::kilt::{
+    did_uri::DidKeyUri,
+    error::CredentialAPIError,
+    runtime::{
+        self,
+        runtime_types::did::did_details::{DidEncryptionKey, DidPublicKey},
+    },
+    KiltConfig,
+};
+
+const LIGHT_DID_ENCRYPTION_FRAGMENT: &str = "encryption";
+
+/// Key agreement keys of full DIDs, valid for one block.
+#[derive(Debug, Default)]
+pub struct KeyAgreementKeyCache {
+    block_hash: Option<H256>,
+    keys: HashMap<String, box_::PublicKey>,
+}
+
+impl KeyAgreementKeyCache {
+    /// Returns the cached key. The cache is cleared once a new block is seen.
+    fn get(&mut self, block_hash: H256, key_uri: &DidKeyUri) -> Option<box_::PublicKey> {
+        if self.block_hash != Some(block_hash) {
+            self.block_hash = Some(block_hash);
+            self.keys.clear();
+        }
+        self.keys.get(&key_uri.to_string()).copied()
+    }
+
+    fn insert(&mut self, block_hash: H256, key_uri: &DidKeyUri, key: box_::PublicKey) {
+        if self.block_hash == Some(block_hash) {
+            self.keys.insert(key_uri.to_string(), key);
+        }
+    }
+}
+
+fn resolve_light_did_key(key_uri: &DidKeyUri) -> Result<box_::PublicKey, CredentialAPIError> {
+    let light_did = key_uri
+        .did()
+        .as_light()
+        .ok_or(CredentialAPIError::Did("Sender is not a light DID"))?;
+
+    if key_uri.fragment() != LIGHT_DID_ENCRYPTION_FRAGMENT {
+        return Err(CredentialAPIError::Did("Not a key agreement key"));
+    }
+    let encryption_key = light_did
+        .encryption_key
+        .ok_or(CredentialAPIError::Did("Light DID has no encryption key"))?;
+    box_::PublicKey::from_slice(&encryption_key)
+        .ok_or(CredentialAPIError::Did("Not a valid public key"))
+}
+
+async fn fetch_full_did_key(
+    key_uri: &DidKeyUri,
+    block_hash: H256,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<box_::PublicKey, CredentialAPIError> {
+    let key_id = key_uri.key_id()?;
+    let details = chain_client
+        .storage()
+        .at(block_hash)
+        .fetch(
+            &runtime::storage()
+                .did()
+                .did(key_uri.did().full_account_id()?),
+        )
+        .await?
+        .ok_or(CredentialAPIError::Did("DID not found"))?;
+
+    if !details.key_agreement_keys.0.contains(&key_id) {
+        return Err(CredentialAPIError::Did("Not a key agreement key"));
+    }
+    let (_, key) = details
+        .public_keys
+        .0
+        .iter()
+        .find(|(id, _)| *id == key_id)
+        .ok_or(CredentialAPIError::Did("Could not get sender public key"))?;
+
+    let DidPublicKey::PublicEncryptionKey(DidEncryptionKey::X25519(key)) = key.key else {
+        return Err(CredentialAPIError::Did("Invalid sender public key"));
+    };
+    box_::PublicKey::from_slice(&key).ok_or(CredentialAPIError::Did("Invalid sender public key"))
+}
+
+/// Returns the key agreement key referenced by a light or full DID key URI.
+pub async fn resolve_key_agreement_key(
+    key_uri: &DidKeyUri,
+    chain_client: &OnlineClient<KiltConfig>,
+    cache: &Mutex<KeyAgreementKeyCache>,
+) -> Result<box_::PublicKey, CredentialAPIError> {
+    if key_uri.did().is_light() {
+        return resolve_light_did_key(key_uri);
+    }
+
+    let block_hash = chain_client
+        .rpc()
+        .block_hash(None)
+        .await?
+        .ok_or(CredentialAPIError::Did("No block to read the DID from"))?;
+    if let Some(key) = cache.lock().await.get(block_hash, key_uri) {
+        return Ok(key);
+    }
+
+    let key = fetch_full_did_key(key_uri, block_hash, chain_client).await?;
+    cache.lock().await.insert(block_hash, key_uri, key);
+    Ok(key)
+}
@@ -0,0 +1,360 @@
+//! Encoding and parsing of KILT light DIDs.
+//! A light DID has the form `did:kilt:light:<auth key type><ss58 address>[:<details>]`.
+//! The optional details are multibase (`z`, base58) encoded and consist of a serialization version byte,
+//! followed by a CBOR map holding the encryption key (`e`) and the service endpoints (`s`).
+
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, Bytes};
+use std::str::FromStr;
+use subxt::ext::sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+
+use crate::kilt::{did_helper::ADDRESS_FORMAT, error::LightDidError};
+
+pub const LIGHT_DID_PREFIX: &str = "did:kilt:light:";
+
+const SERIALIZATION_VERSION: u8 = 0x00;
+const MULTIBASE_BASE58_PREFIX: char = 'z';
+const X25519_KEY_TYPE: &str = "x25519";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightDidAuthKeyType {
+    Sr25519,
+    Ed25519,
+}
+
+impl LightDidAuthKeyType {
+    fn prefix(&self) -> &'static str {
+        match self {
+            LightDidAuthKeyType::Sr25519 => "00",
+            LightDidAuthKeyType::Ed25519 => "01",
+        }
+    }
+
+    fn from_prefix(prefix: &str) -> Result<Self, LightDidError> {
+        match prefix {
+            "00" => Ok(LightDidAuthKeyType::Sr25519),
+            "01" => Ok(LightDidAuthKeyType::Ed25519),
+            _ => Err(LightDidError::UnknownAuthKeyType(prefix.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightDidService {
+    /// Service id without the leading `#`
+    pub id: String,
+    pub types: Vec<String>,
+    pub urls: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightDid {
+    pub auth_key_type: LightDidAuthKeyType,
+    pub auth_key: [u8; 32],
+    pub encryption_key: Option<[u8; 32]>,
+    pub services: Vec<LightDidService>,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct LightDidKeyDetails {
+    #[serde_as(as = "Bytes")]
+    #[serde(rename = "publicKey")]
+    public_key: Vec<u8>,
+    #[serde(rename = "type")]
+    type_: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct LightDidDetails {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    e: Option<LightDidKeyDetails>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    s: Vec<LightDidService>,
+}
+
+impl LightDid {
+    pub fn new(auth_key_type: LightDidAuthKeyType, auth_key: [u8; 32]) -> Self {
+        LightDid {
+            auth_key_type,
+            auth_key,
+            encryption_key: None,
+            services: vec![],
+        }
+    }
+
+    /// SS58 address of the authentication key. This is also the address of the corresponding full DID.
+    pub fn address(&self) -> String {
+        AccountId32::from(self.auth_key).to_ss58check_with_version(ADDRESS_FORMAT.into())
+    }
+
+    fn encode_details(&self) -> Result<Option<String>, LightDidError> {
+        if self.encryption_key.is_none() && self.services.is_empty() {
+            return Ok(None);
+        }
+
+        let details = LightDidDetails {
+            e: self.encryption_key.map(|key| LightDidKeyDetails {
+                public_key: key.to_vec(),
+                type_: X25519_KEY_TYPE.to_string(),
+            }),
+            s: self.services.clone(),
+        };
+
+        let mut bytes = vec![SERIALIZATION_VERSION];
+        bytes.extend(serde_cbor::to_vec(&details).map_err(|e| LightDidError::Cbor(e.to_string()))?);
+        Ok(Some(format!(
+            "{}{}",
+            MULTIBASE_BASE58_PREFIX,
+            bs58::encode(bytes).into_string()
+        )))
+    }
+
+    fn decode_details(encoded: &str) -> Result<LightDidDetails, LightDidError> {
+        let base58_details = encoded
+            .strip_prefix(MULTIBASE_BASE58_PREFIX)
+            .ok_or(LightDidError::MultibasePrefix)?;
+
+        // the details exceed the length the `base58` crate can decode, e.g. with several services
+        let bytes = bs58::decode(base58_details)
+            .into_vec()
+            .map_err(|_| LightDidError::Base58)?;
+
+        let (version, cbor) = bytes.split_first().ok_or(LightDidError::EmptyDetails)?;
+        if *version != SERIALIZATION_VERSION {
+            return Err(LightDidError::SerializationVersion(*version));
+        }
+
+        serde_cbor::from_slice(cbor).map_err(|e| LightDidError::Cbor(e.to_string()))
+    }
+
+    /// Returns the light DID URI.
+    pub fn encode(&self) -> Result<String, LightDidError> {
+        let mut did = format!(
+            "{}{}{}",
+            LIGHT_DID_PREFIX,
+            self.auth_key_type.prefix(),
+            self.address()
+        );
+        if let Some(details) = self.encode_details()? {
+            did.push(':');
+            did.push_str(&details);
+        }
+        Ok(did)
+    }
+}
+
+impl FromStr for LightDid {
+    type Err = LightDidError;
+
+    fn from_str(did: &str) -> Result<Self, Self::Err> {
+        let identifier = did
+            .strip_prefix(LIGHT_DID_PREFIX)
+            .ok_or(LightDidError::Prefix)?;
+
+        let mut parts = identifier.split(':');
+        let auth_part = parts.next().unwrap_or_default();
+        let details_part = parts.next();
+        if parts.next().is_some() {
+            return Err(LightDidError::TooManyParts);
+        }
+
+        if !auth_part.is_char_boundary(2) || auth_part.len() <= 2 {
+            return Err(LightDidError::Address);
+        }
+        let (key_type_prefix, address) = auth_part.split_at(2);
+        let auth_key_type = LightDidAuthKeyType::from_prefix(key_type_prefix)?;
+
+        let (account, format) = AccountId32::from_ss58check_with_version(address)
+            .map_err(|_| LightDidError::Address)?;
+        if format != Ss58AddressFormat::custom(ADDRESS_FORMAT) {
+            return Err(LightDidError::AddressFormat(format.into()));
+        }
+
+        let mut light_did = LightDid::new(auth_key_type, account.into());
+
+        if let Some(details_part) = details_part {
+            let details = LightDid::decode_details(details_part)?;
+
+            if let Some(key) = details.e {
+                if key.type_ != X25519_KEY_TYPE {
+                    return Err(LightDidError::EncryptionKeyType(key.type_));
+                }
+                let public_key = <[u8; 32]>::try_from(key.public_key.as_slice())
+                    .map_err(|_| LightDidError::EncryptionKeyLength(key.public_key.len()))?;
+                light_did.encryption_key = Some(public_key);
+            }
+
+            light_did.services = details.s;
+        }
+
+        Ok(light_did)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Authentication key of the well known development account Alice.
+    const ALICE: [u8; 32] = [
+        0xd4, 0x35, 0x93, 0xc7, 0x15, 0xfd, 0xd3, 0x1c, 0x61, 0x14, 0x1a, 0xbd, 0x04, 0xa9, 0x9f,
+        0xd6, 0x82, 0x2c, 0x85, 0x58, 0x85, 0x4c, 0xcd, 0xe3, 0x9a, 0x56, 0x84, 0xe7, 0xa5, 0x6d,
+        0xa2, 0x7d,
+    ];
+    const ALICE_ADDRESS: &str = "4siJtc4dYq2gPre8Xj6KJcSjVAdi1gmjctUzjf3AwrtNnhvy";
+    /// Alice with the encryption key `0x0102..20` and one service.
+    const ALICE_WITH_DETAILS: &str = "did:kilt:light:004siJtc4dYq2gPre8Xj6KJcSjVAdi1gmjctUzjf3AwrtNnhvy:z14eMxMS7xSK8fMxpGveso3k6HepvoK5CqBJ9xBJoAabpcNf6cmWuH7XAZ8jB1RKxX2KgkZ7AGcYTiVEXAUV6eCAJgamMfKjFmDs847LS1XeYhDwGGkjtniHpSsoA7kT6yG4Hc2vZAesaFBL5ecky53zVCz2jhZradJGmF7tAWckHmoPLVCxnkhANWZyCvFv4Uzvx12ZyrutrBg6h11W1VRNbgWPqFMw3q4h8QMx";
+
+    fn encryption_key() -> [u8; 32] {
+        core::array::from_fn(|i| i as u8 + 1)
+    }
+
+    fn alice_with_details() -> LightDid {
+        let mut light_did = LightDid::new(LightDidAuthKeyType::Sr25519, ALICE);
+        light_did.encryption_key = Some(encryption_key());
+        light_did.services = vec![LightDidService {
+            id: "endpoint".to_string(),
+            types: vec!["KiltPublishedCredentialCollectionV1Type".to_string()],
+            urls: vec!["https://dive.example.com/credentials".to_string()],
+        }];
+        light_did
+    }
+
+    /// Details with the given version byte followed by the CBOR map.
+    fn encode_raw_details(version: u8, cbor: &[u8]) -> String {
+        let mut bytes = vec![version];
+        bytes.extend_from_slice(cbor);
+        format!(
+            "{}{}",
+            MULTIBASE_BASE58_PREFIX,
+            bs58::encode(bytes).into_string()
+        )
+    }
+
+    #[test]
+    fn encodes_a_light_did_without_details() {
+        let light_did = LightDid::new(LightDidAuthKeyType::Sr25519, ALICE);
+        assert_eq!(light_did.address(), ALICE_ADDRESS);
+        assert_eq!(
+            light_did.encode().unwrap(),
+            format!("did:kilt:light:00{}", ALICE_ADDRESS)
+        );
+    }
+
+    #[test]
+    fn encodes_the_encryption_key_and_services() {
+        assert_eq!(alice_with_details().encode().unwrap(), ALICE_WITH_DETAILS);
+    }
+
+    #[test]
+    fn parses_the_encryption_key_and_services() {
+        assert_eq!(
+            LightDid::from_str(ALICE_WITH_DETAILS).unwrap(),
+            alice_with_details()
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut ed25519 = LightDid::new(LightDidAuthKeyType::Ed25519, ALICE);
+        ed25519.encryption_key = Some(encryption_key());
+
+        for light_did in [
+            LightDid::new(LightDidAuthKeyType::Sr25519, ALICE),
+            ed25519,
+            alice_with_details(),
+        ] {
+            let encoded = light_did.encode().unwrap();
+            assert_eq!(LightDid::from_str(&encoded).unwrap(), light_did);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_identifiers() {
+        let cases = [
+            (
+                format!("did:kilt:00{}", ALICE_ADDRESS),
+                LightDidError::Prefix,
+            ),
+            (
+                format!("did:kilt:light:02{}", ALICE_ADDRESS),
+                LightDidError::UnknownAuthKeyType("02".to_string()),
+            ),
+            ("did:kilt:light:00".to_string(), LightDidError::Address),
+            (
+                format!("did:kilt:light:00{}x", ALICE_ADDRESS),
+                LightDidError::Address,
+            ),
+            (
+                format!("{}:extra", ALICE_WITH_DETAILS),
+                LightDidError::TooManyParts,
+            ),
+        ];
+        for (did, expected) in cases {
+            let error = LightDid::from_str(&did).unwrap_err();
+            assert_eq!(error.to_string(), expected.to_string(), "{}", did);
+        }
+    }
+
+    #[test]
+    fn rejects_addresses_of_other_networks() {
+        // Alice in the generic substrate format 42
+        let did = "did:kilt:light:005GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        assert!(matches!(
+            LightDid::from_str(did),
+            Err(LightDidError::AddressFormat(42))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_details() {
+        let did = |details: &str| format!("did:kilt:light:00{}:{}", ALICE_ADDRESS, details);
+
+        assert!(matches!(
+            LightDid::from_str(&did("14eMxMS7")),
+            Err(LightDidError::MultibasePrefix)
+        ));
+        assert!(matches!(
+            LightDid::from_str(&did("z0OIl")),
+            Err(LightDidError::Base58)
+        ));
+        assert!(matches!(
+            LightDid::from_str(&did("z")),
+            Err(LightDidError::EmptyDetails)
+        ));
+        // empty CBOR map
+        assert!(matches!(
+            LightDid::from_str(&did(&encode_raw_details(1, &[0xa0]))),
+            Err(LightDidError::SerializationVersion(1))
+        ));
+        assert!(matches!(
+            LightDid::from_str(&did(&encode_raw_details(0, &[0xff, 0x00]))),
+            Err(LightDidError::Cbor(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_encryption_keys() {
+        let details = |public_key: &[u8], key_type: &str| {
+            let details = LightDidDetails {
+                e: Some(LightDidKeyDetails {
+                    public_key: public_key.to_vec(),
+                    type_: key_type.to_string(),
+                }),
+                s: vec![],
+            };
+            let details = encode_raw_details(0, &serde_cbor::to_vec(&details).unwrap());
+            format!("did:kilt:light:00{}:{}", ALICE_ADDRESS, details)
+        };
+
+        assert!(matches!(
+            LightDid::from_str(&details(&encryption_key()[..16], X25519_KEY_TYPE)),
+            Err(LightDidError::EncryptionKeyLength(16))
+        ));
+        assert!(matches!(
+            LightDid::from_str(&details(&encryption_key(), "ed25519")),
+            Err(LightDidError::EncryptionKeyType(key_type)) if key_type == "ed25519"
+        ));
+    }
+}
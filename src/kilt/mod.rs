@@ -1,6 +1,22 @@
+pub mod attestation;
+pub mod attestation_policy;
+pub mod attestation_watcher;
+pub mod call_decoder;
+pub mod credential_integrity;
+pub mod ctype;
+pub mod delegation;
+pub mod did_document;
 pub mod did_helper;
+pub mod did_lookup;
+pub mod did_signature;
+pub mod did_uri;
 pub mod error;
+pub mod key_resolver;
+pub mod light_did;
+pub mod pending_calls;
+pub mod service_endpoint;
 pub mod tx;
+pub mod web3_name;
 pub mod well_known_did_configuration;
 
 mod utils;
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::kilt::{
+    call_decoder::{decode_call, DecodedCall},
+    error::TxError,
+};
+
+/// Maximum number of calls waiting for an approval. Further calls are refused until the queue shrinks.
+const MAX_PENDING_CALLS: usize = 100;
+
+/// A call which waits for the approval of an admin before it is signed with the payment account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingCall {
+    pub id: Uuid,
+    pub call: String,
+    pub decoded: Option<DecodedCall>,
+    pub created_at: i64,
+}
+
+/// Queue holding all calls which are not yet approved or rejected.
+#[derive(Debug, Default)]
+pub struct PendingCallQueue {
+    calls: HashMap<Uuid, (PendingCall, Vec<u8>)>,
+    /// Calls which are approved and currently submitted
+    submitting: HashSet<Uuid>,
+}
+
+impl PendingCallQueue {
+    /// Adds a call to the queue. Calls that can not be decoded are queued as well, so the admin can decide.
+    pub fn push(&mut self, call: Vec<u8>) -> Result<PendingCall, TxError> {
+        if self.calls.len() >= MAX_PENDING_CALLS {
+            return Err(TxError::PendingCallQueueFull);
+        }
+
+        let decoded = decode_call(&call)
+            .map_err(|e| log::warn!("Queued call could not be decoded: {}", e))
+            .ok();
+
+        let pending_call = PendingCall {
+            id: Uuid::new_v4(),
+            call: format!("0x{}", hex::encode(&call)),
+            decoded,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+
+        self.calls
+            .insert(pending_call.id, (pending_call.clone(), call));
+        Ok(pending_call)
+    }
+
+    /// Returns all pending calls, oldest first.
+    pub fn list(&self) -> Vec<PendingCall> {
+        let mut calls: Vec<PendingCall> = self.calls.values().map(|(c, _)| c.clone()).collect();
+        calls.sort_by_key(|c| c.created_at);
+        calls
+    }
+
+    /// Marks the call as being submitted and returns the raw call bytes.
+    /// The call stays in the queue until [Self::take] or [Self::release] is called.
+    pub fn start_submission(&mut self, id: &Uuid) -> Option<Vec<u8>> {
+        if self.submitting.contains(id) {
+            return None;
+        }
+        let (_, call) = self.calls.get(id)?;
+        self.submitting.insert(*id);
+        Some(call.clone())
+    }
+
+    /// Returns a call whose submission failed to the queue, so it can be approved again.
+    pub fn release(&mut self, id: &Uuid) {
+        self.submitting.remove(id);
+    }
+
+    /// Removes a call from the queue and returns the raw call bytes. Calls being submitted are not removed.
+    pub fn take(&mut self, id: &Uuid) -> Option<Vec<u8>> {
+        if self.submitting.contains(id) {
+            return None;
+        }
+        self.calls.remove(id).map(|(_, call)| call)
+    }
+
+    /// Removes a call after it was submitted successfully.
+    pub fn finish_submission(&mut self, id: &Uuid) {
+        self.submitting.remove(id);
+        self.calls.remove(id);
+    }
+}
@@ -0,0 +1,103 @@
+//! Service endpoints of a full DID and their validation against the limits of the runtime.
+
+use serde::{Deserialize, Serialize};
+use subxt::{utils::AccountId32, OnlineClient};
+
+use crate::kilt::{
+    did_document::get_did_service_endpoints,
+    error::{ServiceEndpointError, TxError},
+    runtime::{self, runtime_types::did::service_endpoints::DidEndpoint},
+    KiltConfig,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceEndpoint {
+    /// Service id without the leading `#`
+    pub id: String,
+    pub types: Vec<String>,
+    pub urls: Vec<String>,
+}
+
+impl From<DidEndpoint> for ServiceEndpoint {
+    fn from(endpoint: DidEndpoint) -> Self {
+        let to_string = |bytes: Vec<u8>| String::from_utf8_lossy(&bytes).into_owned();
+        ServiceEndpoint {
+            id: to_string(endpoint.id.0),
+            types: endpoint
+                .service_types
+                .0
+                .into_iter()
+                .map(|t| to_string(t.0))
+                .collect(),
+            urls: endpoint
+                .urls
+                .0
+                .into_iter()
+                .map(|u| to_string(u.0))
+                .collect(),
+        }
+    }
+}
+
+/// Limits for service endpoints as configured in the `did` pallet of the connected runtime.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceEndpointLimits {
+    pub max_services: u32,
+    pub max_id_length: u32,
+    pub max_types: u32,
+    pub max_type_length: u32,
+    pub max_urls: u32,
+    pub max_url_length: u32,
+}
+
+impl ServiceEndpointLimits {
+    pub fn fetch(chain_client: &OnlineClient<KiltConfig>) -> Result<Self, TxError> {
+        let constants = chain_client.constants();
+        let did = runtime::constants().did();
+
+        Ok(ServiceEndpointLimits {
+            max_services: constants.at(&did.max_number_of_services_per_did())?,
+            max_id_length: constants.at(&did.max_service_id_length())?,
+            max_types: constants.at(&did.max_number_of_types_per_service())?,
+            max_type_length: constants.at(&did.max_service_type_length())?,
+            max_urls: constants.at(&did.max_number_of_urls_per_service())?,
+            max_url_length: constants.at(&did.max_service_url_length())?,
+        })
+    }
+
+    pub fn validate(&self, endpoint: &ServiceEndpoint) -> Result<(), ServiceEndpointError> {
+        if endpoint.id.is_empty() || endpoint.id.len() > self.max_id_length as usize {
+            return Err(ServiceEndpointError::IdLength(self.max_id_length));
+        }
+        if endpoint.types.is_empty() || endpoint.types.len() > self.max_types as usize {
+            return Err(ServiceEndpointError::TypeCount(self.max_types));
+        }
+        if endpoint
+            .types
+            .iter()
+            .any(|t| t.is_empty() || t.len() > self.max_type_length as usize)
+        {
+            return Err(ServiceEndpointError::TypeLength(self.max_type_length));
+        }
+        if endpoint.urls.is_empty() || endpoint.urls.len() > self.max_urls as usize {
+            return Err(ServiceEndpointError::UrlCount(self.max_urls));
+        }
+        if endpoint
+            .urls
+            .iter()
+            .any(|u| u.is_empty() || u.len() > self.max_url_length as usize)
+        {
+            return Err(ServiceEndpointError::UrlLength(self.max_url_length));
+        }
+        Ok(())
+    }
+}
+
+pub async fn get_service_endpoints(
+    did: &AccountId32,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Vec<ServiceEndpoint>, TxError> {
+    let endpoints = get_did_service_endpoints(did, chain_client).await?;
+    Ok(endpoints.into_iter().map(ServiceEndpoint::from).collect())
+}
@@ -19,12 +19,15 @@ use crate::kilt::{
         runtime_types::{
             bounded_collections::bounded_btree_set::BoundedBTreeSet,
             bounded_collections::bounded_vec::BoundedVec,
+            delegation::access_control::DelegationAc,
             did::did_details::DidAuthorizedCallOperation,
             did::did_details::{DidCreationDetails, DidSignature},
             did::service_endpoints::DidEndpoint,
+            runtime_common::authorization::PalletAuthorize,
             sp_core::{ecdsa, ed25519, sr25519},
         },
     },
+    service_endpoint::ServiceEndpoint,
     utils::{calculate_signature, get_current_block, get_next_tx_counter},
     KiltConfig, RuntimeCall,
 };
@@ -153,9 +156,12 @@ pub async fn submit_call(
     Ok(format!("0x{}", hex::encode(progress.extrinsic_hash())))
 }
 
+/// Attests the claim as the DID at `did_address`.
+/// With an `authorization` the attestation is created under the delegation node instead of as a root attester.
 pub async fn create_claim(
     claim_hash: sp_core::H256,
     ctype_hash: sp_core::H256,
+    authorization: Option<sp_core::H256>,
     did_address: &AccountId32,
     chain_client: &OnlineClient<KiltConfig>,
     payer: &PairSigner<KiltConfig, Pair>,
@@ -163,11 +169,21 @@ pub async fn create_claim(
 ) -> Result<Vec<u8>, subxt::Error> {
     let tx_counter = get_next_tx_counter(&chain_client, &did_address).await?;
     let block_number = get_current_block(&chain_client).await?;
+    // the delegation node may be nested, its parents are checked up to the runtime limit
+    let max_checks = chain_client
+        .constants()
+        .at(&runtime::constants().delegation().max_parent_checks())?;
 
     let call = RuntimeCall::Attestation(runtime_types::attestation::pallet::Call::add {
         claim_hash,
         ctype_hash,
-        authorization: None,
+        authorization: authorization.map(|subject_node_id| {
+            PalletAuthorize::Delegation(DelegationAc {
+                subject_node_id,
+                max_checks,
+                __subxt_unused_type_params: Default::default(),
+            })
+        }),
     });
 
     let did_call = DidAuthorizedCallOperation {
@@ -223,28 +239,17 @@ pub async fn create_did(
     Ok(events.extrinsic_hash())
 }
 
-pub async fn add_service_endpoint(
-    url: &str,
-    service_id: &str,
-    service_type: &str,
-    submitter_signer: &PairSigner<KiltConfig, Pair>,
+/// Wraps the call into a [DidAuthorizedCallOperation], signs it with the DID key and submits it.
+/// Fails if the dispatch of the inner call failed.
+async fn submit_did_call(
+    call: RuntimeCall,
+    did_address: &AccountId32,
     did_signer: &PairSigner<KiltConfig, Pair>,
+    submitter_signer: &PairSigner<KiltConfig, Pair>,
     chain_client: &OnlineClient<KiltConfig>,
-) -> Result<(), subxt::Error> {
-    let did_address: subxt::utils::AccountId32 = did_signer.account_id().to_owned().into();
-
-    let service_endpoint = DidEndpoint {
-        id: BoundedVec(service_id.as_bytes().to_vec()),
-        service_types: BoundedVec(vec![BoundedVec(service_type.as_bytes().to_vec())]),
-        urls: BoundedVec(vec![BoundedVec(url.as_bytes().to_vec())]),
-    };
-
-    let tx_counter = get_next_tx_counter(&chain_client, &did_address).await?;
-    let block_number = get_current_block(&chain_client).await?;
-
-    let call = RuntimeCall::Did(runtime_types::did::pallet::Call::add_service_endpoint {
-        service_endpoint,
-    });
+) -> Result<ExtrinsicEvents<KiltConfig>, subxt::Error> {
+    let tx_counter = get_next_tx_counter(chain_client, did_address).await?;
+    let block_number = get_current_block(chain_client).await?;
 
     let did_call = DidAuthorizedCallOperation {
         did: did_address.to_owned(),
@@ -258,56 +263,174 @@ pub async fn add_service_endpoint(
     let final_tx = runtime::tx().did().submit_did_call(did_call, signature);
     let events = submit_tx(chain_client, submitter_signer, &final_tx).await?;
 
-    let update_event = events.find_first::<runtime::did::events::DidUpdated>()?;
+    let dispatched_event = events.find_first::<runtime::did::events::DidCallDispatched>()?;
 
-    if let Some(_) = update_event {
-        log::info!("Service endpoint with url: {:?} added", url);
-        Ok(())
-    } else {
-        log::info!(
-            "Service endpoint with url: {:?} could not be added. Update Event not found",
-            url
-        );
-        Err(subxt::Error::Other("Update Event not found".to_string()))
+    match dispatched_event {
+        Some(runtime::did::events::DidCallDispatched(_, Err(e))) => {
+            log::info!("DID call could not be dispatched: {:?}", e);
+            Err(subxt::Error::Other(format!("DID call failed: {:?}", e)))
+        }
+        Some(_) => Ok(events),
+        None => Err(subxt::Error::Other(
+            "DidCallDispatched Event not found".to_string(),
+        )),
     }
 }
 
-pub async fn remove_service_endpoint(
-    service_id: &str,
-    submitter_signer: &PairSigner<KiltConfig, Pair>,
-    did_signer: &PairSigner<KiltConfig, Pair>,
-    chain_client: &OnlineClient<KiltConfig>,
-) -> Result<(), subxt::Error> {
-    let did_address: subxt::utils::AccountId32 = did_signer.account_id().to_owned().into();
-    let tx_counter = get_next_tx_counter(&chain_client, &did_address).await?;
-    let block_number = get_current_block(&chain_client).await?;
+/// Builder for DID authorized calls, which must succeed or fail together.
+/// Multiple calls are wrapped in `utility.batch_all` and submitted in a single `submit_did_call`.
+#[derive(Debug, Default)]
+pub struct DidCallBatch {
+    calls: Vec<RuntimeCall>,
+}
+
+impl DidCallBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    let call = RuntimeCall::Did(runtime_types::did::pallet::Call::remove_service_endpoint {
+    pub fn with_call(mut self, call: RuntimeCall) -> Self {
+        self.calls.push(call);
+        self
+    }
+
+    /// Signs the batch with the DID key of `did_signer` and submits it paid by `submitter_signer`.
+    pub async fn submit(
+        self,
+        submitter_signer: &PairSigner<KiltConfig, Pair>,
+        did_signer: &PairSigner<KiltConfig, Pair>,
+        chain_client: &OnlineClient<KiltConfig>,
+    ) -> Result<ExtrinsicEvents<KiltConfig>, subxt::Error> {
+        let did_address: AccountId32 = did_signer.account_id().to_owned().into();
+        self.submit_for(&did_address, submitter_signer, did_signer, chain_client)
+            .await
+    }
+
+    /// Like [DidCallBatch::submit], for DIDs where `did_signer` is not the authentication key, e.g. the delegation key.
+    pub async fn submit_for(
+        mut self,
+        did_address: &AccountId32,
+        submitter_signer: &PairSigner<KiltConfig, Pair>,
+        did_signer: &PairSigner<KiltConfig, Pair>,
+        chain_client: &OnlineClient<KiltConfig>,
+    ) -> Result<ExtrinsicEvents<KiltConfig>, subxt::Error> {
+        let call = match self.calls.len() {
+            0 => return Err(subxt::Error::Other("DID call batch is empty".to_string())),
+            // a single call does not need the batch overhead
+            1 => self.calls.remove(0),
+            _ => RuntimeCall::Utility(runtime_types::pallet_utility::pallet::Call::batch_all {
+                calls: self.calls,
+            }),
+        };
+
+        submit_did_call(
+            call,
+            did_address,
+            did_signer,
+            submitter_signer,
+            chain_client,
+        )
+        .await
+    }
+}
+
+pub fn add_service_endpoint_call(endpoint: &ServiceEndpoint) -> RuntimeCall {
+    let to_bounded = |values: &[String]| {
+        BoundedVec(
+            values
+                .iter()
+                .map(|v| BoundedVec(v.as_bytes().to_vec()))
+                .collect(),
+        )
+    };
+    let service_endpoint = DidEndpoint {
+        id: BoundedVec(endpoint.id.as_bytes().to_vec()),
+        service_types: to_bounded(&endpoint.types),
+        urls: to_bounded(&endpoint.urls),
+    };
+
+    RuntimeCall::Did(runtime_types::did::pallet::Call::add_service_endpoint { service_endpoint })
+}
+
+pub fn remove_service_endpoint_call(service_id: &str) -> RuntimeCall {
+    RuntimeCall::Did(runtime_types::did::pallet::Call::remove_service_endpoint {
         service_id: BoundedVec(service_id.into()),
+    })
+}
+
+/// Revokes the attestation with the given claim hash. The attestation stays on chain, but is marked as revoked.
+pub async fn revoke_attestation(
+    claim_hash: H256,
+    did_address: &AccountId32,
+    chain_client: &OnlineClient<KiltConfig>,
+    payer: &PairSigner<KiltConfig, Pair>,
+    signer: &PairSigner<KiltConfig, Pair>,
+) -> Result<H256, subxt::Error> {
+    let call = RuntimeCall::Attestation(runtime_types::attestation::pallet::Call::revoke {
+        claim_hash,
+        authorization: None,
     });
 
-    let did_call = DidAuthorizedCallOperation {
-        did: did_address.to_owned(),
-        tx_counter,
-        call,
-        block_number,
-        submitter: submitter_signer.account_id().to_owned().into(),
-    };
+    let events = submit_did_call(call, did_address, signer, payer, chain_client).await?;
 
-    let signature = calculate_signature(&did_call.encode(), did_signer);
-    let final_tx = runtime::tx().did().submit_did_call(did_call, signature);
-    let events = submit_tx(chain_client, submitter_signer, &final_tx).await?;
+    if events
+        .find_first::<runtime::attestation::events::AttestationRevoked>()?
+        .is_none()
+    {
+        return Err(subxt::Error::Other("Revoked Event not found".to_string()));
+    }
 
-    let update_event = events.find_first::<runtime::did::events::DidUpdated>()?;
+    log::info!("Attestation with root hash {:?} revoked", claim_hash);
+    Ok(events.extrinsic_hash())
+}
 
-    if let Some(_) = update_event {
-        log::info!("Service endpoint with service id: {:?} removed", service_id);
-        Ok(())
-    } else {
-        log::info!(
-            "Service endpoint with service id: {:?} could not be added. Update Event not found",
-            service_id
-        );
-        Err(subxt::Error::Other("Update Event not found".to_string()))
+/// Removes the attestation with the given claim hash and releases the deposit.
+pub async fn remove_attestation(
+    claim_hash: H256,
+    did_address: &AccountId32,
+    chain_client: &OnlineClient<KiltConfig>,
+    payer: &PairSigner<KiltConfig, Pair>,
+    signer: &PairSigner<KiltConfig, Pair>,
+) -> Result<H256, subxt::Error> {
+    let call = RuntimeCall::Attestation(runtime_types::attestation::pallet::Call::remove {
+        claim_hash,
+        authorization: None,
+    });
+
+    let events = submit_did_call(call, did_address, signer, payer, chain_client).await?;
+
+    if events
+        .find_first::<runtime::attestation::events::AttestationRemoved>()?
+        .is_none()
+    {
+        return Err(subxt::Error::Other("Removed Event not found".to_string()));
     }
+
+    log::info!("Attestation with root hash {:?} removed", claim_hash);
+    Ok(events.extrinsic_hash())
+}
+
+/// Removes the attestation as deposit owner. This is not a DID call, the payer signs it directly.
+pub async fn reclaim_attestation_deposit(
+    claim_hash: H256,
+    chain_client: &OnlineClient<KiltConfig>,
+    payer: &PairSigner<KiltConfig, Pair>,
+) -> Result<H256, subxt::Error> {
+    let tx = runtime::tx().attestation().reclaim_deposit(claim_hash);
+    let events = submit_tx(chain_client, payer, &tx).await?;
+
+    if events
+        .find_first::<runtime::attestation::events::DepositReclaimed>()?
+        .is_none()
+    {
+        return Err(subxt::Error::Other(
+            "DepositReclaimed Event not found".to_string(),
+        ));
+    }
+
+    log::info!(
+        "Deposit of attestation with root hash {:?} reclaimed",
+        claim_hash
+    );
+    Ok(events.extrinsic_hash())
 }
@@ -0,0 +1,106 @@
+//! Web3 names are unique, human readable aliases of full DIDs, managed by the `web3_names` pallet.
+
+use serde::{Deserialize, Serialize};
+use subxt::{utils::AccountId32, OnlineClient};
+
+use crate::kilt::{
+    did_uri::DidUri,
+    error::Web3NameError,
+    runtime::{
+        self,
+        runtime_types::{
+            bounded_collections::bounded_vec::BoundedVec,
+            pallet_web3_names::{pallet::Call, web3_name::AsciiWeb3Name},
+        },
+    },
+    KiltConfig, RuntimeCall,
+};
+
+/// Prefix used when a web3 name is referenced in a DID Document, e.g. as `alsoKnownAs`.
+pub const WEB3_NAME_PREFIX: &str = "w3n:";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3NameStatus {
+    pub name: String,
+    pub available: bool,
+    pub banned: bool,
+    pub owner: Option<DidUri>,
+}
+
+/// Checks the name against the length limits of the runtime and the allowed characters
+/// (lowercase ASCII letters, digits, `-` and `_`).
+pub fn validate_web3_name(
+    name: &str,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<(), Web3NameError> {
+    let constants = chain_client.constants();
+    let web3_names = runtime::constants().web3_names();
+    let min = constants.at(&web3_names.min_name_length())?;
+    let max = constants.at(&web3_names.max_name_length())?;
+
+    if name.len() < min as usize || name.len() > max as usize {
+        return Err(Web3NameError::Length { min, max });
+    }
+
+    let valid_characters = name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid_characters {
+        return Err(Web3NameError::InvalidCharacters(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Returns the web3 name owned by the DID, if any.
+pub async fn query_web3_name(
+    did: &AccountId32,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Option<String>, subxt::Error> {
+    let name = chain_client
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&runtime::storage().web3_names().names(did))
+        .await?;
+
+    Ok(name.map(|name| String::from_utf8_lossy(&name.0 .0).into_owned()))
+}
+
+pub async fn query_web3_name_status(
+    name: &str,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Web3NameStatus, Web3NameError> {
+    validate_web3_name(name, chain_client)?;
+
+    let storage = chain_client.storage().at_latest().await?;
+    let encoded_name = web3_name_bytes(name);
+    let owner = storage
+        .fetch(&runtime::storage().web3_names().owner(&encoded_name))
+        .await?;
+    let banned = storage
+        .fetch(&runtime::storage().web3_names().banned(&encoded_name))
+        .await?
+        .is_some();
+
+    Ok(Web3NameStatus {
+        name: name.to_string(),
+        available: owner.is_none() && !banned,
+        banned,
+        owner: owner.map(|ownership| DidUri::full(ownership.owner)),
+    })
+}
+
+fn web3_name_bytes(name: &str) -> AsciiWeb3Name {
+    AsciiWeb3Name(BoundedVec(name.as_bytes().to_vec()))
+}
+
+pub fn claim_web3_name_call(name: &str) -> RuntimeCall {
+    RuntimeCall::Web3Names(Call::claim {
+        name: BoundedVec(name.as_bytes().to_vec()),
+    })
+}
+
+pub fn release_web3_name_call() -> RuntimeCall {
+    RuntimeCall::Web3Names(Call::release_by_owner)
+}
@@ -33,14 +33,12 @@
 //! }
 //! ```
 
-use blake2::{Blake2b, Digest};
-use hmac::digest::typenum::U32;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sp_core::Pair;
 use std::collections::HashMap;
 
-type Blake2b256 = Blake2b<U32>;
+use crate::kilt::credential_integrity::{calculate_root_hash, hash_statement, salted_hash};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CredentialSubject {
@@ -86,33 +84,20 @@ impl WellKnownDidConfig {
         ];
         let hashes = normalized
             .iter()
-            .map(|part| -> String {
-                let mut hasher = Blake2b256::new();
-                hasher.update(part.as_str());
-                hex_encode(hasher.finalize())
-            })
+            .map(|part| hash_statement(part))
             .collect::<Vec<String>>();
         let (_nonce_map, salted_hashes) = {
             let mut nonces = HashMap::new();
             let mut salted_hashes = Vec::new();
             hashes.iter().for_each(|hash| {
                 let nonce = uuid::Uuid::new_v4().to_string();
-                let mut hasher = Blake2b256::new();
-                hasher.update(nonce.as_str());
-                hasher.update(hash.as_str());
-                let salted_hash = hex_encode(hasher.finalize());
-                salted_hashes.push(salted_hash);
+                salted_hashes.push(salted_hash(&nonce, hash));
                 nonces.insert(hash.clone(), nonce);
             });
             (nonces, salted_hashes)
         };
-        let mut hasher = Blake2b256::new();
 
-        for salted_hash in salted_hashes.iter() {
-            hasher.update(hex_decode(salted_hash.as_str())?);
-        }
-
-        let root_hash = hasher.finalize();
+        let root_hash = hex_decode(&calculate_root_hash(&salted_hashes, &[], None)?)?;
         let signature = signer.sign(&root_hash);
         let proof = Proof {
             type_: "KILTSelfSigned2020".to_string(),
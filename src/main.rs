@@ -11,45 +11,54 @@ use actix_cors::Cors;
 use actix_files as fs;
 use actix_session::{
     config::{CookieContentSecurity, PersistentSession},
-    storage::CookieSessionStore,
     SessionMiddleware,
 };
-use actix_web::{
-    cookie::{time::Duration, Key},
-    middleware::Logger,
-    web, App, HttpServer,
-};
+use actix_web::{cookie::time::Duration, middleware::Logger, web, App, HttpServer};
 use anyhow::Context;
 use clap::Parser;
 use routes::{
-    get_challenge_scope, get_claim_scope, get_credential_scope, get_did_scope, get_payment_scope,
-    get_use_case_scope,
+    get_attestation_review_scope, get_attestation_scope, get_challenge_scope, get_claim_scope,
+    get_credential_scope, get_credential_store_scope, get_ctype_scope, get_delegation_scope,
+    get_did_lookup_scope, get_did_scope, get_notification_scope, get_payment_scope,
+    get_resolve_scope, get_use_case_scope, get_verifier_scope, get_web3_name_scope,
 };
 use sodiumoxide::crypto::box_::SecretKey;
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 use subxt::{
     ext::sp_core::{crypto::Ss58Codec, sr25519::Pair},
     tx::PairSigner,
     utils::AccountId32,
 };
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
+use uuid::Uuid;
 
 use crate::{
     configuration::Configuration,
     device::{
-        exists_key_file, get_existing_key_pair_manager, init_key_pair_manager,
+        attestation_quota::AttestationQuota,
+        exists_key_file,
+        file_manager::migrate_credential_store,
+        get_existing_key_pair_manager, init_key_pair_manager,
         key_manager::{KeyManager, PairKeyManager},
+        session_store::ServerSessionStore,
     },
+    dto::CredentialNotification,
     kilt::{
-        did_helper::{ADDRESS_FORMAT, DID_PREFIX},
+        attestation_policy::AttestationPolicy,
+        attestation_watcher::watch_attestation_events,
+        did_helper::ADDRESS_FORMAT,
+        did_uri::{DidKeyUri, DidUri},
+        key_resolver::KeyAgreementKeyCache,
+        pending_calls::PendingCallQueue,
         well_known_did_configuration::WellKnownDidConfigData,
         KiltConfig,
     },
-    routes::get_well_known_did_config_scope,
+    routes::{get_well_known_did_config_scope, message_guard::MessageGuard},
 };
 
 const SERVICE_ENDPOINT_TYPE: &'static str = "KiltPublishedCredentialCollectionV1Type";
 const USE_CASE_SERVICE_ENDPOINT_ID: &'static str = "dive";
+const NOTIFICATION_CHANNEL_CAPACITY: usize = 32;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -71,36 +80,70 @@ pub struct AppState {
     // App name for creating credentials
     pub app_name: String,
     // Public key
-    pub session_encryption_public_key_uri: String,
+    pub session_encryption_public_key_uri: DidKeyUri,
     // Secret key for encryption. Needed for credential api
     pub secret_key: SecretKey,
     // key pair for creating credentials
     pub signer: Arc<PairSigner<KiltConfig, Pair>>,
     // Did for creating credentials
     pub did_attester: AccountId32,
+    /// Delegation key of the attester DID, used to manage delegation hierarchies
+    pub delegation_signer: Arc<PairSigner<KiltConfig, Pair>>,
     /// Type for service Endpoint
     pub kilt_service_endpoint_type: String,
     ///Service Endpoint ID for use case participation
     pub use_case_service_endpoint_id: String,
     /// Used data to create the well known did config
     pub well_known_did_config_data: Arc<Mutex<WellKnownDidConfigData>>,
+    /// If true, calls for the payment account need an approval before they are signed
+    pub require_call_approval: bool,
+    /// Calls waiting for an approval
+    pub pending_calls: Arc<Mutex<PendingCallQueue>>,
+    /// If true, every attestation request is queued for a manual review
+    pub require_attestation_review: bool,
+    /// Rules deciding which attestation requests are attested without a review
+    pub attestation_policy: Arc<AttestationPolicy>,
+    /// Daily attestation counts, including the attestations being submitted
+    pub attestation_quota: Arc<Mutex<AttestationQuota>>,
+    /// CTypes requested from the claimer when acting as verifier
+    pub verifier_ctypes: Vec<String>,
+    /// Attesters accepted when acting as verifier. Empty trusts every attester
+    pub verifier_trusted_attesters: Vec<DidUri>,
+    /// Channel for credential status changes, which are pushed to the UI
+    pub notifications: broadcast::Sender<CredentialNotification>,
+    /// Received and sent message ids, used to reject replayed messages
+    pub message_guard: Arc<Mutex<MessageGuard>>,
+    /// Key agreement keys of full DIDs resolved at the latest block
+    pub key_agreement_keys: Arc<Mutex<KeyAgreementKeyCache>>,
+    /// Bearer token of the admin routes. `None` disables them
+    pub admin_token: Option<String>,
+    /// Serializes the writes to the credential store file
+    pub credential_store_lock: Arc<Mutex<()>>,
+    /// Serializes the writes to the mailbox of the claimers
+    pub claimer_mailbox_lock: Arc<Mutex<()>>,
+    /// Serializes the writes to the attestation records file
+    pub attestation_record_lock: Arc<Mutex<()>>,
+    /// Serializes the writes to the attestation reviews file and holds the reviews being approved or rejected
+    pub attestation_review_lock: Arc<Mutex<HashSet<Uuid>>>,
 }
 
-pub async fn run(
-    source_dir: String,
-    wss_endpoint: String,
-    port: u16,
-    key_manager: PairKeyManager,
-    auth_endpoint: String,
-    attester_endpoint: String,
-    auth_client_id: String,
-    redirect_url: String,
-    session_encryption_public_key_uri: String,
-    secret_key: SecretKey,
-    signer: PairSigner<KiltConfig, Pair>,
-    did_attester: AccountId32,
-    well_known_did_config_data: WellKnownDidConfigData,
-) -> anyhow::Result<()> {
+pub async fn run(config: Configuration, key_manager: PairKeyManager) -> anyhow::Result<()> {
+    let well_known_did_config_data = config.get_well_known_did_config_data();
+    let secret_key = config.get_secret_key()?;
+    let did_attester = config.get_did()?;
+    let signer = config.get_credential_signer()?;
+    let delegation_signer = config.get_delegation_signer()?;
+    let attestation_policy = config.get_attestation_policy()?;
+    let session_key = config.get_session_key()?;
+    let session_store = ServerSessionStore::new(config.session_store_path.clone())?;
+    let session_ttl = config.session_ttl;
+    let session_cookie_secure = config.session_cookie_secure;
+    let source_dir = config.front_end_path;
+    let wss_endpoint = config.wss_address;
+    let port = config.port;
+    let require_call_approval = config.require_call_approval;
+    let require_attestation_review = config.require_attestation_review;
+
     let payment_signer = key_manager.get_payment_account_signer();
     let payment_account_id = payment_signer.account_id();
     let did_auth_signer = key_manager.clone().get_did_auth_signer();
@@ -110,46 +153,86 @@ pub async fn run(
     let did_addr = did_account_id.to_ss58check_with_version(ADDRESS_FORMAT.into());
 
     log::info!("payment_account_id: {}", payment_addr);
-    log::info!("Olibox DID: {}{}", DID_PREFIX, did_addr);
+    log::info!("Olibox DID: {}", DidUri::full(did_account_id.clone()));
 
     log::info!("Connected to: {}", wss_endpoint);
 
     log::info!("Source dir: {}", source_dir);
 
+    if require_call_approval {
+        log::info!("Calls for the payment account require an approval");
+    }
+
+    if require_attestation_review {
+        log::info!("Attestation requests require a manual review");
+    }
+
+    if config.admin_token.is_none() {
+        log::warn!("No admin token configured, admin routes are disabled");
+    }
+
+    migrate_credential_store()?;
+    let credential_store_lock = Arc::new(Mutex::new(()));
+
+    let (notifications, _) = broadcast::channel(NOTIFICATION_CHANNEL_CAPACITY);
+
+    tokio::spawn(watch_attestation_events(
+        wss_endpoint.clone(),
+        notifications.clone(),
+        credential_store_lock.clone(),
+    ));
+
     let app_state = AppState {
         key_manager: Arc::new(Mutex::new(key_manager)),
         jwt_token: Arc::new(Mutex::new(String::new())),
         signer: Arc::new(signer),
         well_known_did_config_data: Arc::new(Mutex::new(well_known_did_config_data)),
+        pending_calls: Arc::new(Mutex::new(PendingCallQueue::default())),
+        message_guard: Arc::new(Mutex::new(MessageGuard::new(config.message_max_age))),
+        key_agreement_keys: Arc::new(Mutex::new(KeyAgreementKeyCache::default())),
+        attestation_policy: Arc::new(attestation_policy),
+        attestation_quota: Arc::new(Mutex::new(AttestationQuota::default())),
         app_name: "Olibox".to_string(),
         wss_endpoint,
-        attester_endpoint,
-        auth_client_id,
-        auth_endpoint,
+        attester_endpoint: config.attester_endpoint,
+        auth_client_id: config.auth_client_id,
+        auth_endpoint: config.auth_endpoint,
         payment_addr,
         did_addr,
-        redirect_url,
-        session_encryption_public_key_uri,
+        redirect_url: config.redirect_url,
+        session_encryption_public_key_uri: config.session_encryption_public_key_uri,
         secret_key,
         did_attester,
+        delegation_signer: Arc::new(delegation_signer),
         kilt_service_endpoint_type: String::from(SERVICE_ENDPOINT_TYPE),
         use_case_service_endpoint_id: String::from(USE_CASE_SERVICE_ENDPOINT_ID),
+        require_call_approval,
+        require_attestation_review,
+        verifier_ctypes: config.verifier_ctypes,
+        verifier_trusted_attesters: config.verifier_trusted_attesters,
+        notifications,
+        admin_token: config.admin_token,
+        credential_store_lock,
+        claimer_mailbox_lock: Arc::new(Mutex::new(())),
+        attestation_record_lock: Arc::new(Mutex::new(())),
+        attestation_review_lock: Arc::new(Mutex::new(HashSet::new())),
     };
 
     // if a thread receives a poisoned lock we panic the main thread.
     utils::set_panic_hook();
 
+    let server_session_store = session_store.clone();
     HttpServer::new(move || {
         App::new()
             .wrap(Cors::permissive())
             .wrap(
-                SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                SessionMiddleware::builder(session_store.clone(), session_key.clone())
                     .cookie_content_security(CookieContentSecurity::Private)
                     .cookie_http_only(true)
-                    .cookie_secure(false)
+                    .cookie_secure(session_cookie_secure)
                     .cookie_name("olibox".to_string())
                     .session_lifecycle(
-                        PersistentSession::default().session_ttl(Duration::seconds(60)),
+                        PersistentSession::default().session_ttl(Duration::seconds(session_ttl)),
                     )
                     .build(),
             )
@@ -163,18 +246,41 @@ pub async fn run(
             .service(get_payment_scope())
             // Credential routes
             .service(get_credential_scope())
+            // Credentials held by the device
+            .service(get_credential_store_scope())
             // Well known did config
             .service(get_well_known_did_config_scope())
             //Challenge
             .service(get_challenge_scope())
             //Use case routes
             .service(get_use_case_scope())
+            // Attestation management routes
+            .service(get_attestation_scope())
+            // Credential status notifications
+            .service(get_notification_scope())
+            // DID resolution
+            .service(get_resolve_scope())
+            // Web3 names
+            .service(get_web3_name_scope())
+            // Account links
+            .service(get_did_lookup_scope())
+            // Delegation hierarchies
+            .service(get_delegation_scope())
+            // CTypes
+            .service(get_ctype_scope())
+            // Manual review of attestation requests
+            .service(get_attestation_review_scope())
+            // Credential verification
+            .service(get_verifier_scope())
             // Frontend
             .service(fs::Files::new("/", &source_dir).index_file("index.html"))
     })
     .bind(("0.0.0.0", port))?
     .run()
     .await?;
+
+    // sessions changed shortly before the shutdown are not written by the background task
+    server_session_store.flush()?;
     Ok(())
 }
 
@@ -186,21 +292,6 @@ async fn main() -> anyhow::Result<()> {
 
     let config = Configuration::parse();
 
-    let well_known_did_config_data = config.get_well_known_did_config_data();
-
-    let secret_key = config.get_secret_key()?;
-    let did_attester = config.get_did()?;
-
-    let signer = config.get_credential_signer()?;
-    let source_dir = config.front_end_path;
-    let wss_endpoint = config.wss_address;
-    let port = config.port;
-    let auth_endpoint = config.auth_endpoint;
-    let attester_endpoint = config.attester_endpoint;
-    let auth_client_id = config.auth_client_id;
-    let redirect_url = config.redirect_url;
-    let session_encryption_public_key_uri = config.session_encryption_public_key_uri;
-
     let key_manager = {
         if exists_key_file() {
             get_existing_key_pair_manager()
@@ -210,22 +301,7 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
-    log::info!("Staring Server on port: {}", port);
+    log::info!("Staring Server on port: {}", config.port);
 
-    run(
-        source_dir,
-        wss_endpoint,
-        port,
-        key_manager,
-        auth_endpoint,
-        attester_endpoint,
-        auth_client_id,
-        redirect_url,
-        session_encryption_public_key_uri,
-        secret_key,
-        signer,
-        did_attester,
-        well_known_did_config_data,
-    )
-    .await
+    run(config, key_manager).await
 }
@@ -0,0 +1,51 @@
+//! Authentication of the admin routes, e.g. the approval of queued calls and attestation requests.
+//! Admin requests carry the configured token as `Authorization: Bearer <token>`.
+//! Without a configured token the admin routes are disabled.
+
+use actix_web::{dev::Payload, http::header::AUTHORIZATION, web, FromRequest, HttpRequest};
+use sha2::{Digest, Sha256};
+use std::future::{ready, Ready};
+
+use crate::{error::ServerError, AppState};
+
+/// Extractor which only succeeds for requests carrying the admin token.
+pub struct Admin;
+
+/// Compares the hashes of both tokens, so the comparison does not leak the length of the matching prefix.
+fn token_matches(provided: &str, expected: &str) -> bool {
+    let provided = Sha256::digest(provided.as_bytes());
+    let expected = Sha256::digest(expected.as_bytes());
+    provided
+        .iter()
+        .zip(expected.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+fn authorize(req: &HttpRequest) -> Result<Admin, ServerError> {
+    let token = req
+        .app_data::<web::Data<AppState>>()
+        .and_then(|app_state| app_state.admin_token.clone())
+        .ok_or(ServerError::Unauthorized("Admin routes are disabled"))?;
+
+    let provided = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(ServerError::Unauthorized("Missing admin token"))?;
+
+    if !token_matches(provided, &token) {
+        return Err(ServerError::Unauthorized("Invalid admin token"));
+    }
+    Ok(Admin)
+}
+
+impl FromRequest for Admin {
+    type Error = ServerError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authorize(req))
+    }
+}
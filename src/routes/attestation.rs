@@ -0,0 +1,197 @@
+use actix_web::{delete, get, post, web, HttpResponse, Responder, Scope};
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+
+use crate::{
+    device::{
+        file_manager::{get_attestation_records, get_credentials, save_attestation_record},
+        key_manager::KeyManager,
+    },
+    dto::{AttestationAction, AttestationRecord},
+    error::ServerError,
+    kilt::{
+        attestation::{query_attestation, AttestationStatus},
+        connect,
+        error::TxError,
+        tx::{reclaim_attestation_deposit, remove_attestation, revoke_attestation},
+    },
+    routes::admin::Admin,
+    AppState,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CredentialAttestationStatus {
+    root_hash: String,
+    attestation: Option<AttestationStatus>,
+}
+
+pub(crate) fn parse_root_hash(root_hash: &str) -> Result<H256, ServerError> {
+    let bytes = hex::decode(root_hash.trim_start_matches("0x").trim())?;
+    if bytes.len() != 32 {
+        Err(actix_web::error::ErrorBadRequest(
+            "Root hash has a wrong format",
+        ))?
+    }
+    Ok(H256::from_slice(&bytes))
+}
+
+/// Stores the outcome of the attestation transaction and passes the result through.
+async fn record_outcome(
+    app_state: &AppState,
+    root_hash: &H256,
+    action: AttestationAction,
+    result: Result<H256, subxt::Error>,
+) -> Result<String, ServerError> {
+    let record = AttestationRecord {
+        root_hash: format!("{:?}", root_hash),
+        action,
+        success: result.is_ok(),
+        tx: result
+            .as_ref()
+            .ok()
+            .map(|tx| format!("0x{}", hex::encode(tx))),
+        error: result.as_ref().err().map(|e| e.to_string()),
+        created_at: chrono::Utc::now().timestamp(),
+    };
+
+    let tx = record.tx.clone();
+    {
+        let _records = app_state.attestation_record_lock.lock().await;
+        save_attestation_record(record)?;
+    }
+
+    result?;
+    Ok(tx.unwrap_or_default())
+}
+
+#[get("")]
+async fn get_records(_admin: Admin) -> Result<impl Responder, ServerError> {
+    let records = get_attestation_records()?;
+    Ok(HttpResponse::Ok().json(records))
+}
+
+#[get("/status")]
+async fn get_device_attestation_status(
+    app_state: web::Data<AppState>,
+) -> Result<impl Responder, ServerError> {
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let credentials = get_credentials()?;
+
+    let mut statuses = Vec::with_capacity(credentials.len());
+    for stored in credentials {
+        let claim_hash = parse_root_hash(&stored.credential.root_hash)?;
+        statuses.push(CredentialAttestationStatus {
+            root_hash: stored.credential.root_hash,
+            attestation: query_attestation(claim_hash, &chain_client).await?,
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(statuses))
+}
+
+#[get("/status/{root_hash}")]
+async fn get_attestation_status(
+    app_state: web::Data<AppState>,
+    root_hash: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let claim_hash = parse_root_hash(&root_hash)?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let status = query_attestation(claim_hash, &chain_client)
+        .await?
+        .ok_or_else(|| TxError::AttestationNotFound(root_hash.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(status))
+}
+
+#[post("/{root_hash}/revoke")]
+async fn revoke(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    root_hash: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let claim_hash = parse_root_hash(&root_hash)?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let payer = app_state
+        .key_manager
+        .lock()
+        .await
+        .get_payment_account_signer();
+
+    let result = revoke_attestation(
+        claim_hash,
+        &app_state.did_attester,
+        &chain_client,
+        &payer,
+        &app_state.signer,
+    )
+    .await;
+
+    let tx = record_outcome(&app_state, &claim_hash, AttestationAction::Revoke, result).await?;
+    Ok(HttpResponse::Ok().json(tx))
+}
+
+#[delete("/{root_hash}")]
+async fn remove(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    root_hash: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let claim_hash = parse_root_hash(&root_hash)?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let payer = app_state
+        .key_manager
+        .lock()
+        .await
+        .get_payment_account_signer();
+
+    let result = remove_attestation(
+        claim_hash,
+        &app_state.did_attester,
+        &chain_client,
+        &payer,
+        &app_state.signer,
+    )
+    .await;
+
+    let tx = record_outcome(&app_state, &claim_hash, AttestationAction::Remove, result).await?;
+    Ok(HttpResponse::Ok().json(tx))
+}
+
+#[post("/{root_hash}/reclaim")]
+async fn reclaim_deposit(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    root_hash: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let claim_hash = parse_root_hash(&root_hash)?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let payer = app_state
+        .key_manager
+        .lock()
+        .await
+        .get_payment_account_signer();
+
+    let result = reclaim_attestation_deposit(claim_hash, &chain_client, &payer).await;
+
+    let tx = record_outcome(
+        &app_state,
+        &claim_hash,
+        AttestationAction::ReclaimDeposit,
+        result,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(tx))
+}
+
+pub fn get_attestation_scope() -> Scope {
+    web::scope("/api/v1/attestation")
+        .service(get_records)
+        .service(get_device_attestation_status)
+        .service(get_attestation_status)
+        .service(revoke)
+        .service(remove)
+        .service(reclaim_deposit)
+}
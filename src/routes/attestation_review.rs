@@ -0,0 +1,174 @@
+use actix_web::{delete, get, post, web, HttpResponse, Responder, Scope};
+use uuid::Uuid;
+
+use crate::{
+    device::file_manager::{
+        get_attestation_reviews, remove_attestation_review, save_claimer_message,
+    },
+    dto::{AttestationReview, ClaimerMessage},
+    error::ServerError,
+    kilt::{connect, error::TxError, key_resolver::resolve_key_agreement_key},
+    routes::{
+        admin::Admin,
+        credential::{attest_credential, attestation_authorization, encrypt_message},
+        dto::{Message, MessageBody},
+    },
+    AppState,
+};
+
+fn get_attestation_review(id: &Uuid) -> Result<AttestationReview, ServerError> {
+    get_attestation_reviews()?
+        .into_iter()
+        .find(|review| review.id == *id)
+        .ok_or_else(|| TxError::AttestationReviewNotFound(id.to_string()).into())
+}
+
+/// Marks the review as in progress, so it can not be approved or rejected a second time meanwhile.
+async fn start_review(app_state: &AppState, id: &Uuid) -> Result<AttestationReview, ServerError> {
+    let mut in_progress = app_state.attestation_review_lock.lock().await;
+    let review = get_attestation_review(id)?;
+    if !in_progress.insert(*id) {
+        return Err(TxError::AttestationReviewInProgress(id.to_string()).into());
+    }
+    Ok(review)
+}
+
+/// Removes the review from the queue if it was handled. A failed review stays in the queue and can be retried.
+async fn finish_review(
+    app_state: &AppState,
+    id: &Uuid,
+    result: Result<(), ServerError>,
+) -> Result<(), ServerError> {
+    let mut in_progress = app_state.attestation_review_lock.lock().await;
+    if let Err(e) = result {
+        in_progress.remove(id);
+        return Err(e);
+    }
+    // a handled review stays marked if it can not be removed, so it is not handled twice
+    remove_attestation_review(id)?;
+    in_progress.remove(id);
+    Ok(())
+}
+
+#[get("")]
+async fn get_reviews(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+) -> Result<impl Responder, ServerError> {
+    let reviews = {
+        let _reviews = app_state.attestation_review_lock.lock().await;
+        get_attestation_reviews()?
+    };
+    Ok(HttpResponse::Ok().json(reviews))
+}
+
+#[get("/{id}")]
+async fn get_review(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    id: web::Path<Uuid>,
+) -> Result<impl Responder, ServerError> {
+    let review = {
+        let _reviews = app_state.attestation_review_lock.lock().await;
+        get_attestation_review(&id)?
+    };
+    Ok(HttpResponse::Ok().json(review))
+}
+
+/// Attests the credential of the request. The request stays in the queue if the attestation fails.
+#[post("/{id}")]
+async fn approve_review(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    id: web::Path<Uuid>,
+) -> Result<impl Responder, ServerError> {
+    let review = start_review(&app_state, &id).await?;
+    let result = attest_review(&app_state, &review).await;
+    finish_review(&app_state, &id, result).await?;
+
+    log::info!("Attestation request {} approved", id);
+    Ok(HttpResponse::Ok().json("ok"))
+}
+
+async fn attest_review(
+    app_state: &AppState,
+    review: &AttestationReview,
+) -> Result<(), ServerError> {
+    let credential = &review.content.credential;
+
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let authorization = attestation_authorization(app_state, credential, &chain_client).await?;
+    // an approved request is attested regardless of the quota, but still counted for it
+    let today = chrono::Utc::now().date_naive().to_string();
+    let reservation = app_state
+        .attestation_quota
+        .lock()
+        .await
+        .reserve(&credential.claim.ctype_hash, &today);
+    attest_credential(
+        app_state,
+        credential,
+        authorization,
+        reservation,
+        &chain_client,
+    )
+    .await
+}
+
+/// Rejects the request. The encrypted `reject-attestation` message waits in the mailbox until the claimer fetches it.
+#[delete("/{id}")]
+async fn reject_review(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    id: web::Path<Uuid>,
+) -> Result<impl Responder, ServerError> {
+    let review = start_review(&app_state, &id).await?;
+    let result = send_rejection(&app_state, &review).await;
+    // the review is only removed once the claimer can fetch the rejection
+    finish_review(&app_state, &id, result).await?;
+
+    log::info!("Attestation request {} rejected", id);
+    Ok(HttpResponse::Ok().json("ok"))
+}
+
+async fn send_rejection(
+    app_state: &AppState,
+    review: &AttestationReview,
+) -> Result<(), ServerError> {
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let claimer_pubkey = resolve_key_agreement_key(
+        &review.sender_key_uri,
+        &chain_client,
+        &app_state.key_agreement_keys,
+    )
+    .await?;
+
+    let msg = Message::new(
+        MessageBody::RejectAttestation(review.content.credential.root_hash.clone()),
+        app_state.session_encryption_public_key_uri.did().clone(),
+        review.sender_key_uri.did().clone(),
+    )
+    .with_reply_to(&review.message_id);
+    let message = encrypt_message(
+        app_state,
+        &msg,
+        review.sender_key_uri.clone(),
+        &claimer_pubkey,
+    )?;
+
+    let _mailbox = app_state.claimer_mailbox_lock.lock().await;
+    save_claimer_message(ClaimerMessage {
+        receiver: review.sender_key_uri.did().to_full(),
+        message,
+        created_at: chrono::Utc::now().timestamp(),
+    })?;
+    Ok(())
+}
+
+pub fn get_attestation_review_scope() -> Scope {
+    web::scope("/api/v1/attestation-review")
+        .service(get_reviews)
+        .service(get_review)
+        .service(approve_review)
+        .service(reject_review)
+}
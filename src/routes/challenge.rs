@@ -1,36 +1,127 @@
-use actix_session::Session;
-use actix_web::{get, post, web, HttpResponse, Scope};
+use actix_session::{Session, SessionExt};
+use actix_web::{dev::Payload, get, post, web, FromRequest, HttpRequest, HttpResponse, Scope};
 use serde::{Deserialize, Serialize};
 use sodiumoxide::crypto::box_;
+use std::future::{ready, Ready};
 use uuid::Uuid;
 
 use crate::{
     error::ServerError,
-    kilt::error::CredentialAPIError,
+    kilt::{
+        connect, did_uri::DidKeyUri, error::CredentialAPIError,
+        key_resolver::resolve_key_agreement_key,
+    },
     utils::{hex_nonce, prefixed_hex},
     AppState,
 };
 
+/// Handshakes of one browser session which can be open at the same time, e.g. in several dApp tabs.
+pub(crate) const MAX_PENDING_CHALLENGES: usize = 8;
+
+/// Sessions one browser can keep at the same time. The oldest session is dropped first.
+pub(crate) const MAX_SESSIONS: usize = 8;
+/// Header naming the session of a request. Only needed if the browser has started several sessions.
+pub(crate) const SESSION_ID_HEADER: &str = "X-Session-Id";
+
+const CHALLENGES_KEY: &str = "challenges";
+const SESSIONS_KEY: &str = "sessions";
+
+/// Version of the PubSub session of the KILT extension API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionVersion {
+    V1,
+    V2,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ChallengeData {
     #[serde(rename = "dAppName")]
     pub app_name: String,
     #[serde(rename = "dAppEncryptionKeyUri")]
-    pub encryption_key_uri: String,
+    pub encryption_key_uri: DidKeyUri,
     pub challenge: Vec<u8>,
 }
 
+/// Session started by the extension. `PubSubSessionV1` of the KILT extension API sends the key of the
+/// extension as `encryptionKeyId`, `PubSubSessionV2` as `encryptionKeyUri`.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChallengeResponse {
-    #[serde(alias = "encryptionKeyId")]
-    pub encryption_key_uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption_key_id: Option<DidKeyUri>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption_key_uri: Option<DidKeyUri>,
     #[serde(with = "prefixed_hex")]
     pub encrypted_challenge: Vec<u8>,
     #[serde(with = "hex_nonce")]
     pub nonce: box_::Nonce,
 }
 
+impl ChallengeResponse {
+    /// Returns the session version and the encryption key URI of the extension.
+    fn negotiate(&self) -> Result<(SessionVersion, &DidKeyUri), CredentialAPIError> {
+        match (&self.encryption_key_id, &self.encryption_key_uri) {
+            (Some(key_uri), None) => Ok((SessionVersion::V1, key_uri)),
+            (None, Some(key_uri)) => Ok((SessionVersion::V2, key_uri)),
+            (Some(_), Some(_)) => Err(CredentialAPIError::Challenge("Ambiguous session version")),
+            (None, None) => Err(CredentialAPIError::Challenge("No encryption key URI")),
+        }
+    }
+}
+
+/// Session established by a handshake. The id is returned to the client, which names the session with it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub encryption_key_uri: DidKeyUri,
+    pub version: SessionVersion,
+}
+
+/// Extractor for the session of the request, named by [SESSION_ID_HEADER].
+/// Without the header the only session of the browser is used.
+pub struct DappSession(pub SessionInfo);
+
+fn established_session(req: &HttpRequest) -> Result<DappSession, ServerError> {
+    let sessions = req
+        .get_session()
+        .get::<Vec<SessionInfo>>(SESSIONS_KEY)
+        .map_err(|_| CredentialAPIError::Challenge("Session not set"))?
+        .unwrap_or_default();
+
+    let session = match req.headers().get(SESSION_ID_HEADER) {
+        Some(session_id) => {
+            let session_id = session_id
+                .to_str()
+                .map_err(|_| CredentialAPIError::Challenge("Invalid session id"))?;
+            sessions
+                .into_iter()
+                .find(|session| session.session_id == session_id)
+        }
+        None if sessions.len() > 1 => {
+            return Err(CredentialAPIError::Challenge("Session id required").into());
+        }
+        None => sessions.into_iter().next(),
+    };
+    session
+        .map(DappSession)
+        .ok_or_else(|| CredentialAPIError::Challenge("Session not set").into())
+}
+
+impl FromRequest for DappSession {
+    type Error = ServerError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(established_session(req))
+    }
+}
+
+/// Checks the decrypted challenge. Extensions encrypt either the challenge bytes or its hex encoding.
+fn matches_challenge(challenge: &[u8], decrypted: &[u8]) -> bool {
+    challenge == decrypted || format!("0x{}", hex::encode(challenge)).as_bytes() == decrypted
+}
+
 #[get("")]
 async fn challenge_handler(
     state: web::Data<AppState>,
@@ -42,9 +133,18 @@ async fn challenge_handler(
 
     let challenge = Uuid::new_v4().as_bytes().to_vec();
 
+    let mut challenges = session
+        .get::<Vec<Vec<u8>>>(CHALLENGES_KEY)
+        .map_err(|_| CredentialAPIError::Challenge("Session not set"))?
+        .unwrap_or_default();
+    challenges.push(challenge.clone());
+    if challenges.len() > MAX_PENDING_CHALLENGES {
+        challenges.remove(0);
+    }
+
     session
-        .insert("challenge", challenge.clone())
-        .map_err(|_| CredentialAPIError::Challenge("Could not insert encryption key"))?;
+        .insert(CHALLENGES_KEY, challenges)
+        .map_err(|_| CredentialAPIError::Challenge("Could not insert challenge"))?;
 
     let challenge_data = ChallengeData {
         challenge,
@@ -61,14 +161,16 @@ async fn challenge_response_handler(
     challenge_response: web::Json<ChallengeResponse>,
     session: Session,
 ) -> Result<HttpResponse, ServerError> {
-    let challenge = session
-        .get::<Vec<u8>>("challenge")
+    let mut challenges = session
+        .get::<Vec<Vec<u8>>>(CHALLENGES_KEY)
         .map_err(|_| CredentialAPIError::Challenge("Session not set"))?
         .ok_or(CredentialAPIError::Challenge("Session not set"))?;
 
-    let encryption_key_uri = &challenge_response.encryption_key_uri;
+    let (version, encryption_key_uri) = challenge_response.negotiate()?;
+    let chain_client = connect(&state.wss_endpoint).await?;
     let others_pubkey =
-        crate::kilt::did_helper::parse_encryption_key_from_lightdid(encryption_key_uri)?;
+        resolve_key_agreement_key(encryption_key_uri, &chain_client, &state.key_agreement_keys)
+            .await?;
 
     let decrypted_challenge = box_::open(
         &challenge_response.encrypted_challenge,
@@ -78,21 +180,124 @@ async fn challenge_response_handler(
     )
     .map_err(|_| CredentialAPIError::Challenge("Unable to decrypt"))?;
 
-    if decrypted_challenge != challenge {
+    let Some(index) = challenges
+        .iter()
+        .position(|challenge| matches_challenge(challenge, &decrypted_challenge))
+    else {
         return Err(ServerError::CredentialAPI(CredentialAPIError::Challenge(
             "Challenge do not match",
         )));
-    }
+    };
 
+    // every challenge answers one handshake
+    challenges.remove(index);
     session
-        .insert("encryption_key_uri", encryption_key_uri)
-        .map_err(|_| CredentialAPIError::Challenge("Could not insert encryption key"))?;
+        .insert(CHALLENGES_KEY, challenges)
+        .map_err(|_| CredentialAPIError::Challenge("Could not insert challenge"))?;
 
-    Ok(HttpResponse::Ok().json("Ok"))
+    // every handshake starts its own session, so several dApps can use the same browser
+    let info = SessionInfo {
+        session_id: Uuid::new_v4().to_string(),
+        encryption_key_uri: encryption_key_uri.clone(),
+        version,
+    };
+    let mut sessions = session
+        .get::<Vec<SessionInfo>>(SESSIONS_KEY)
+        .map_err(|_| CredentialAPIError::Challenge("Session not set"))?
+        .unwrap_or_default();
+    sessions.push(info.clone());
+    if sessions.len() > MAX_SESSIONS {
+        sessions.remove(0);
+    }
+    session
+        .insert(SESSIONS_KEY, sessions)
+        .map_err(|_| CredentialAPIError::Challenge("Could not insert session"))?;
+
+    log::debug!(
+        "{:?} session {} started with {}",
+        version,
+        info.session_id,
+        encryption_key_uri
+    );
+    Ok(HttpResponse::Ok().json(info))
+}
+
+/// Returns the encryption key of the extension and the negotiated session version.
+#[get("/session")]
+async fn get_session_info(session: DappSession) -> Result<HttpResponse, ServerError> {
+    Ok(HttpResponse::Ok().json(session.0))
 }
 
 pub fn get_challenge_scope() -> Scope {
     web::scope("/api/v1/challenge")
         .service(challenge_handler)
         .service(challenge_response_handler)
+        .service(get_session_info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIGHT_KEY_URI: &str =
+        "did:kilt:light:004siJtc4dYq2gPre8Xj6KJcSjVAdi1gmjctUzjf3AwrtNnhvy#encryption";
+    const FULL_KEY_URI: &str = "did:kilt:4siJtc4dYq2gPre8Xj6KJcSjVAdi1gmjctUzjf3AwrtNnhvy#0x8f3a8b1c2d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8";
+    const ENCRYPTED_CHALLENGE: &str = "0x0102030405";
+    const NONCE: &str = "0x000102030405060708090a0b0c0d0e0f1011121314151617";
+
+    fn parse(json: serde_json::Value) -> ChallengeResponse {
+        serde_json::from_str(&json.to_string()).expect("challenge response should deserialize")
+    }
+
+    #[test]
+    fn negotiates_v1_from_encryption_key_id() {
+        let response = parse(serde_json::json!({
+            "encryptionKeyId": LIGHT_KEY_URI,
+            "encryptedChallenge": ENCRYPTED_CHALLENGE,
+            "nonce": NONCE,
+        }));
+
+        let (version, key_uri) = response.negotiate().unwrap();
+        assert_eq!(version, SessionVersion::V1);
+        assert_eq!(key_uri.to_string(), LIGHT_KEY_URI);
+        assert_eq!(response.encrypted_challenge, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn negotiates_v2_from_encryption_key_uri() {
+        let response = parse(serde_json::json!({
+            "encryptionKeyUri": FULL_KEY_URI,
+            "encryptedChallenge": ENCRYPTED_CHALLENGE,
+            "nonce": NONCE,
+        }));
+
+        let (version, key_uri) = response.negotiate().unwrap();
+        assert_eq!(version, SessionVersion::V2);
+        assert_eq!(key_uri.to_string(), FULL_KEY_URI);
+    }
+
+    #[test]
+    fn rejects_missing_or_ambiguous_key() {
+        let missing = parse(serde_json::json!({
+            "encryptedChallenge": ENCRYPTED_CHALLENGE,
+            "nonce": NONCE,
+        }));
+        assert!(missing.negotiate().is_err());
+
+        let ambiguous = parse(serde_json::json!({
+            "encryptionKeyId": LIGHT_KEY_URI,
+            "encryptionKeyUri": FULL_KEY_URI,
+            "encryptedChallenge": ENCRYPTED_CHALLENGE,
+            "nonce": NONCE,
+        }));
+        assert!(ambiguous.negotiate().is_err());
+    }
+
+    #[test]
+    fn matches_raw_and_hex_encoded_challenge() {
+        let challenge = [0xde, 0xad, 0xbe, 0xef];
+        assert!(matches_challenge(&challenge, &challenge));
+        assert!(matches_challenge(&challenge, b"0xdeadbeef"));
+        assert!(!matches_challenge(&challenge, &[0xde, 0xad]));
+    }
 }
@@ -2,22 +2,41 @@ use actix_web::{get, post, web, HttpResponse, Responder, Scope};
 
 use crate::{
     device::{
-        file_manager::{get_claim_content, save_claim_content},
+        file_manager::{get_credential, get_credentials, save_credential},
         key_manager::KeyManager,
     },
-    dto::Credential,
+    dto::{Credential, CredentialStatus, StoredCredential},
     error::ServerError,
     http_client::{check_jwt_health, login_to_open_did, post_claim_to_attester},
-    kilt::connect,
+    kilt::{
+        attestation::query_attestation,
+        connect,
+        error::{CredentialAPIError, TxError},
+    },
+    routes::{attestation::parse_root_hash, ctype::validate_claim, dto::AttestationSubmission},
     AppState,
 };
 
+/// Returns the most recently added credential of the device.
+fn get_latest_credential() -> Result<StoredCredential, ServerError> {
+    get_credentials()?
+        .pop()
+        .ok_or_else(|| CredentialAPIError::CredentialNotFound("no credential".to_string()).into())
+}
+
 #[get("")]
 async fn get_base_claim() -> Result<impl Responder, ServerError> {
-    let claim = get_claim_content()?;
+    let claim = get_latest_credential()?.credential;
     Ok(HttpResponse::Ok().json(claim))
 }
 
+/// Returns the status of the base claim, which is kept up to date by the attestation watcher.
+#[get("/status")]
+async fn get_base_claim_status() -> Result<impl Responder, ServerError> {
+    let status = get_latest_credential()?.status;
+    Ok(HttpResponse::Ok().json(status))
+}
+
 #[post("")]
 async fn post_base_claim(
     body: web::Json<Credential>,
@@ -32,6 +51,13 @@ async fn post_base_claim(
     let sign_pair = key_manager.get_did_auth_signer();
     let chain_client = connect(&app_state.wss_endpoint).await?;
 
+    validate_claim(
+        &base_claim.claim.ctype_hash,
+        &base_claim.claim.contents,
+        &chain_client,
+    )
+    .await?;
+
     let mut jwt_token = app_state.jwt_token.lock().await;
 
     let is_jwt_healthy = check_jwt_health(&jwt_token);
@@ -50,13 +76,68 @@ async fn post_base_claim(
 
     post_claim_to_attester(&jwt_token, &base_claim, &app_state.attester_endpoint).await?;
 
-    save_claim_content(&base_claim)?;
+    {
+        let _store = app_state.credential_store_lock.lock().await;
+        save_credential(StoredCredential::new(base_claim.clone()))?;
+    }
 
     Ok(HttpResponse::Ok().json(base_claim))
 }
 
+/// Accepts the attestation of a device credential, checks it against the chain and stores it with the credential.
+#[post("/attestation")]
+async fn post_attestation(
+    app_state: web::Data<AppState>,
+    body: web::Json<AttestationSubmission>,
+) -> Result<impl Responder, ServerError> {
+    let attestation = body.into_inner().into_attestation()?;
+    let mut stored = get_credential(&attestation.claim_hash)?
+        .ok_or_else(|| CredentialAPIError::CredentialNotFound(attestation.claim_hash.clone()))?;
+    let credential = &stored.credential;
+
+    if !attestation
+        .ctype_hash
+        .eq_ignore_ascii_case(&credential.claim.ctype_hash)
+    {
+        return Err(CredentialAPIError::AttestationMismatch("CType hash").into());
+    }
+
+    let root_hash = parse_root_hash(&credential.root_hash)?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let on_chain = query_attestation(root_hash, &chain_client)
+        .await
+        .map_err(TxError::from)?
+        .ok_or_else(|| TxError::AttestationNotFound(credential.root_hash.clone()))?;
+
+    if on_chain.revoked {
+        return Err(CredentialAPIError::AttestationMismatch("attestation is revoked").into());
+    }
+    if !on_chain
+        .ctype_hash
+        .eq_ignore_ascii_case(&credential.claim.ctype_hash)
+    {
+        return Err(CredentialAPIError::AttestationMismatch("on-chain CType hash").into());
+    }
+    if on_chain.attester.to_full() != attestation.owner.to_full() {
+        return Err(CredentialAPIError::AttestationMismatch("attester").into());
+    }
+
+    log::info!("Attested credential {} stored", credential.root_hash);
+
+    stored.status = CredentialStatus::Attested;
+    stored.attestation = Some(on_chain);
+    {
+        let _store = app_state.credential_store_lock.lock().await;
+        save_credential(stored.clone())?;
+    }
+
+    Ok(HttpResponse::Ok().json(stored))
+}
+
 pub fn get_claim_scope() -> Scope {
     web::scope("/api/v1/claim")
         .service(get_base_claim)
+        .service(get_base_claim_status)
         .service(post_base_claim)
+        .service(post_attestation)
 }
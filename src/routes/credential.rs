@@ -1,14 +1,27 @@
-use actix_session::Session;
 use actix_web::{get, post, web, HttpResponse, Responder, Scope};
 use sodiumoxide::crypto::box_;
 use sp_core::H256;
+use subxt::OnlineClient;
+use uuid::Uuid;
 
 use crate::{
-    device::key_manager::KeyManager,
+    device::{
+        attestation_quota::QuotaReservation,
+        file_manager::{save_attestation_review, take_claimer_messages},
+        key_manager::KeyManager,
+    },
+    dto::AttestationReview,
     error::ServerError,
     http_client::{check_jwt_health, get_credentials_from_attester, login_to_open_did},
-    kilt::{connect, error::CredentialAPIError},
-    routes::dto::*,
+    kilt::{
+        connect,
+        delegation::{parse_delegation_id, verify_attestation_delegation},
+        did_uri::DidKeyUri,
+        error::{CredentialAPIError, CredentialIntegrityError, TxError},
+        key_resolver::resolve_key_agreement_key,
+        KiltConfig,
+    },
+    routes::{challenge::DappSession, ctype::validate_claim, dto::*},
     AppState,
 };
 
@@ -39,28 +52,43 @@ async fn get_credential(app_state: web::Data<AppState>) -> Result<impl Responder
     Ok(HttpResponse::Ok().json(data))
 }
 
+/// Encrypts the message for the receiver with the session encryption key.
+pub(crate) fn encrypt_message(
+    app_state: &AppState,
+    message: &Message,
+    receiver_key_uri: DidKeyUri,
+    receiver_public_key: &box_::PublicKey,
+) -> Result<EncryptedMessage, ServerError> {
+    let msg_json = serde_json::to_string(message)?;
+    let nonce = box_::gen_nonce();
+    let encrypted_msg = box_::seal(
+        msg_json.as_bytes(),
+        &nonce,
+        receiver_public_key,
+        &app_state.secret_key,
+    );
+    Ok(EncryptedMessage {
+        cipher_text: encrypted_msg,
+        nonce,
+        sender_key_uri: app_state.session_encryption_public_key_uri.clone(),
+        receiver_key_uri,
+    })
+}
+
 #[post("/terms")]
 async fn get_terms(
     state: web::Data<AppState>,
-    session: Session,
+    session: DappSession,
     claim: web::Json<Claim>,
 ) -> Result<HttpResponse, ServerError> {
-    let sender_key_uri = session
-        .get::<String>("encryption_key_uri")
-        .map_err(|_| CredentialAPIError::Challenge("Session not set"))?
-        .ok_or(CredentialAPIError::Challenge("Session not set"))?;
+    let sender_key_uri = session.0.encryption_key_uri;
 
+    let chain_client = connect(&state.wss_endpoint).await?;
     let others_pubkey =
-        crate::kilt::did_helper::parse_encryption_key_from_lightdid(&sender_key_uri)?;
+        resolve_key_agreement_key(&sender_key_uri, &chain_client, &state.key_agreement_keys)
+            .await?;
 
-    let encryption_key_uri = state.session_encryption_public_key_uri.clone();
-
-    let sender = encryption_key_uri
-        .split('#')
-        .collect::<Vec<&str>>()
-        .first()
-        .ok_or_else(|| CredentialAPIError::Attestation("Invalid Key URI for sender"))?
-        .to_owned();
+    let sender = state.session_encryption_public_key_uri.did().clone();
 
     let content = SubmitTermsMessageContent {
         c_types: vec![claim.0.ctype_hash.clone()],
@@ -68,34 +96,107 @@ async fn get_terms(
         legitimations: Some(vec![]),
     };
 
-    let msg = Message {
-        body: MessageBody {
-            content,
-            type_: "submit-terms".to_string(),
-        },
-        created_at: 0,
-        sender: sender.to_string(),
-        receiver: sender_key_uri.clone(),
-        message_id: uuid::Uuid::new_v4().to_string(),
-        in_reply_to: None,
-        references: None,
-    };
+    let msg = Message::new(
+        MessageBody::SubmitTerms(content),
+        sender,
+        sender_key_uri.did().clone(),
+    );
 
-    let msg_json = serde_json::to_string(&msg).unwrap();
-    let msg_bytes = msg_json.as_bytes();
-    let our_secretkey = state.secret_key.clone();
-    let nonce = box_::gen_nonce();
-    let encrypted_msg = box_::seal(msg_bytes, &nonce, &others_pubkey, &our_secretkey);
-    let response = EncryptedMessage {
-        cipher_text: encrypted_msg,
-        nonce,
-        sender_key_uri: encryption_key_uri.clone(),
-        receiver_key_uri: sender_key_uri,
-    };
+    let response = encrypt_message(&state, &msg, sender_key_uri, &others_pubkey)?;
+    state.message_guard.lock().await.record_sent(&msg);
 
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Returns the root hash and the CType hash of the credential.
+fn credential_hashes(credential: &Credential) -> Result<(H256, H256), ServerError> {
+    let ctype_hash = hex::decode(credential.claim.ctype_hash.trim_start_matches("0x").trim())?;
+    let claim_hash = hex::decode(credential.root_hash.trim_start_matches("0x").trim())?;
+    if claim_hash.len() != 32 || ctype_hash.len() != 32 {
+        Err(actix_web::error::ErrorBadRequest(
+            "Claim hash or ctype hash have a wrong format",
+        ))?
+    }
+    Ok((H256::from_slice(&claim_hash), H256::from_slice(&ctype_hash)))
+}
+
+/// Returns the delegation node to attest the credential under, if the attester is allowed to use it.
+pub(crate) async fn attestation_authorization(
+    app_state: &AppState,
+    credential: &Credential,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Option<H256>, ServerError> {
+    let Some(delegation_id) = &credential.delegation_id else {
+        return Ok(None);
+    };
+    let (_, ctype_hash) = credential_hashes(credential)?;
+    let delegation_id = parse_delegation_id(delegation_id).map_err(TxError::from)?;
+    verify_attestation_delegation(
+        delegation_id,
+        &app_state.did_attester,
+        ctype_hash,
+        chain_client,
+    )
+    .await
+    .map_err(TxError::from)?;
+    Ok(Some(delegation_id))
+}
+
+/// Attests the credential on chain. The reserved quota slot is counted if the attestation succeeds
+/// and released otherwise.
+pub(crate) async fn attest_credential(
+    app_state: &AppState,
+    credential: &Credential,
+    authorization: Option<H256>,
+    reservation: QuotaReservation,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<(), ServerError> {
+    let (claim_hash, ctype_hash) = credential_hashes(credential)?;
+
+    let payer = app_state
+        .key_manager
+        .lock()
+        .await
+        .get_payment_account_signer();
+
+    let result = crate::kilt::tx::create_claim(
+        claim_hash,
+        ctype_hash,
+        authorization,
+        &app_state.did_attester,
+        chain_client,
+        &payer,
+        &app_state.signer,
+    )
+    .await;
+
+    app_state
+        .attestation_quota
+        .lock()
+        .await
+        .release(reservation, result.is_ok())?;
+    result?;
+    Ok(())
+}
+
+/// Returns the encrypted messages waiting for the claimer of the session, e.g. the rejection of a reviewed request.
+/// Every message is returned once.
+#[get("/messages")]
+async fn get_claimer_messages(
+    app_state: web::Data<AppState>,
+    session: DappSession,
+) -> Result<HttpResponse, ServerError> {
+    let messages = {
+        let _mailbox = app_state.claimer_mailbox_lock.lock().await;
+        take_claimer_messages(session.0.encryption_key_uri.did())?
+    };
+    let messages: Vec<EncryptedMessage> = messages
+        .into_iter()
+        .map(|message| message.message)
+        .collect();
+    Ok(HttpResponse::Ok().json(messages))
+}
+
 #[post("")]
 async fn request_attestation(
     app_state: web::Data<AppState>,
@@ -103,9 +204,10 @@ async fn request_attestation(
 ) -> Result<HttpResponse, ServerError> {
     let chain_client = connect(&app_state.wss_endpoint).await?;
 
-    let others_pubkey = crate::kilt::did_helper::get_encryption_key_from_fulldid_key_uri(
+    let others_pubkey = resolve_key_agreement_key(
         &encrypted_message.sender_key_uri,
         &chain_client,
+        &app_state.key_agreement_keys,
     )
     .await?;
 
@@ -117,32 +219,115 @@ async fn request_attestation(
     )
     .map_err(|_| CredentialAPIError::Attestation("Unable to decrypt"))?;
 
-    let decrypted_message: Message<RequestAttestationMessageContent> =
-        serde_json::from_slice(&decrypted_message_bytes).unwrap();
+    let decrypted_message: Message = serde_json::from_slice(&decrypted_message_bytes)?;
 
-    let credential = decrypted_message.body.content.credential;
-
-    let ctype_hash = hex::decode(credential.claim.ctype_hash.trim_start_matches("0x").trim())?;
-    let claim_hash = hex::decode(credential.root_hash.trim_start_matches("0x").trim())?;
-    if claim_hash.len() != 32 || ctype_hash.len() != 32 {
-        Err(actix_web::error::ErrorBadRequest(
-            "Claim hash or ctype hash have a wrong format",
-        ))?
-    }
-
-    let payer = app_state
-        .key_manager
+    // a request for an attestation answers the terms sent by `get_terms`
+    let ticket = app_state
+        .message_guard
         .lock()
         .await
-        .get_payment_account_signer();
+        .check(&decrypted_message, Some("submit-terms"))
+        .map_err(CredentialAPIError::from)?;
 
-    crate::kilt::tx::create_claim(
-        H256::from_slice(&claim_hash),
-        H256::from_slice(&ctype_hash),
-        &app_state.did_attester,
+    let result = handle_attestation_request(
+        &app_state,
+        &encrypted_message.sender_key_uri,
+        decrypted_message,
         &chain_client,
-        &payer,
-        &app_state.signer,
+    )
+    .await;
+
+    // the terms stay open if the request failed, so the claimer can send it again
+    let mut message_guard = app_state.message_guard.lock().await;
+    match result {
+        Ok(_) => message_guard.commit(ticket),
+        Err(_) => message_guard.release(&ticket),
+    }
+    result
+}
+
+/// Attests the credential of the request or queues it for a review.
+async fn handle_attestation_request(
+    app_state: &AppState,
+    sender_key_uri: &DidKeyUri,
+    decrypted_message: Message,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<HttpResponse, ServerError> {
+    let content = match decrypted_message.body {
+        MessageBody::RequestAttestation(content) => content,
+        body => {
+            return Err(CredentialAPIError::UnexpectedMessage(body.type_name()).into());
+        }
+    };
+    let credential = &content.credential;
+
+    credential
+        .verify_integrity()
+        .map_err(CredentialAPIError::from)?;
+
+    let sender = sender_key_uri.did();
+    if credential.claim.owner.to_full() != sender.to_full() {
+        return Err(
+            CredentialAPIError::from(CredentialIntegrityError::OwnerMismatch {
+                owner: credential.claim.owner.to_string(),
+                sender: sender.to_string(),
+            })
+            .into(),
+        );
+    }
+
+    validate_claim(
+        &credential.claim.ctype_hash,
+        &credential.claim.contents,
+        chain_client,
+    )
+    .await?;
+
+    // attest under the delegation node of the credential, if the attester is allowed to
+    let authorization = attestation_authorization(app_state, credential, chain_client).await?;
+
+    // the quota stays locked until the slot is reserved, so concurrent requests cannot exceed it
+    let today = chrono::Utc::now().date_naive().to_string();
+    let mut quota = app_state.attestation_quota.lock().await;
+    let attested_today = quota.count(&credential.claim.ctype_hash, &today)?;
+    let violations = app_state.attestation_policy.check(
+        &credential.claim.ctype_hash,
+        &credential.claim.owner,
+        &credential.claim.contents,
+        attested_today,
+    );
+    if app_state.require_attestation_review || !violations.is_empty() {
+        drop(quota);
+        let review = AttestationReview {
+            id: Uuid::new_v4(),
+            sender_key_uri: sender_key_uri.clone(),
+            message_id: decrypted_message.message_id,
+            content,
+            violations,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+        log::info!(
+            "Attestation request {} queued for review: {:?}",
+            review.id,
+            review.violations
+        );
+        {
+            let _reviews = app_state.attestation_review_lock.lock().await;
+            save_attestation_review(review)?;
+        }
+        // the review id stays with the admin, the claimer only learns that the request is queued
+        return Ok(HttpResponse::Accepted().json("Attestation request is waiting for a review"));
+    }
+
+    let reservation = quota.reserve(&credential.claim.ctype_hash, &today);
+    drop(quota);
+
+    attest_credential(
+        app_state,
+        credential,
+        authorization,
+        reservation,
+        chain_client,
     )
     .await?;
 
@@ -153,5 +338,6 @@ pub fn get_credential_scope() -> Scope {
     web::scope("/api/v1/credential")
         .service(get_credential)
         .service(get_terms)
+        .service(get_claimer_messages)
         .service(request_attestation)
 }
@@ -0,0 +1,120 @@
+use actix_web::{delete, get, post, web, HttpResponse, Responder, Scope};
+use subxt::OnlineClient;
+
+use crate::{
+    device::file_manager::{get_credential, get_credentials, remove_credential, save_credential},
+    dto::{Credential, CredentialFilter, CredentialStatus, StoredCredential},
+    error::ServerError,
+    kilt::{
+        attestation::query_attestation,
+        connect,
+        error::{CredentialAPIError, TxError},
+        KiltConfig,
+    },
+    routes::attestation::parse_root_hash,
+    AppState,
+};
+
+fn get_stored_credential(root_hash: &str) -> Result<StoredCredential, ServerError> {
+    get_credential(root_hash)?
+        .ok_or_else(|| CredentialAPIError::CredentialNotFound(root_hash.to_string()).into())
+}
+
+/// Updates the status and the attestation of the credential from the chain.
+async fn refresh_attestation(
+    stored: &mut StoredCredential,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<(), ServerError> {
+    let root_hash = parse_root_hash(&stored.credential.root_hash)?;
+    let attestation = query_attestation(root_hash, chain_client)
+        .await
+        .map_err(TxError::from)?;
+
+    stored.status = match &attestation {
+        Some(attestation) if attestation.revoked => CredentialStatus::Revoked,
+        Some(_) => CredentialStatus::Attested,
+        // an attestation which disappeared was removed, otherwise the credential is not attested yet
+        None if stored.attestation.is_some() => CredentialStatus::Removed,
+        None => CredentialStatus::Pending,
+    };
+    if attestation.is_some() {
+        stored.attestation = attestation;
+    }
+    Ok(())
+}
+
+/// Lists the credentials of the device, optionally filtered by CType, attester and status.
+#[get("")]
+async fn get_stored_credentials(
+    filter: web::Query<CredentialFilter>,
+) -> Result<impl Responder, ServerError> {
+    let credentials: Vec<StoredCredential> = get_credentials()?
+        .into_iter()
+        .filter(|stored| filter.matches(stored))
+        .collect();
+    Ok(HttpResponse::Ok().json(credentials))
+}
+
+#[get("/{root_hash}")]
+async fn get_stored_credential_by_hash(
+    root_hash: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let stored = get_stored_credential(&root_hash)?;
+    Ok(HttpResponse::Ok().json(stored))
+}
+
+/// Adds a credential to the device, e.g. a renewal or a credential of another attester.
+#[post("")]
+async fn add_credential(
+    app_state: web::Data<AppState>,
+    credential: web::Json<Credential>,
+) -> Result<impl Responder, ServerError> {
+    let mut stored = StoredCredential::new(credential.into_inner());
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    refresh_attestation(&mut stored, &chain_client).await?;
+    {
+        let _store = app_state.credential_store_lock.lock().await;
+        save_credential(stored.clone())?;
+    }
+
+    log::info!("Credential {} added", stored.credential.root_hash);
+    Ok(HttpResponse::Ok().json(stored))
+}
+
+/// Updates the status of the credential from the chain.
+#[post("/{root_hash}/refresh")]
+async fn refresh_credential(
+    app_state: web::Data<AppState>,
+    root_hash: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let mut stored = get_stored_credential(&root_hash)?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    refresh_attestation(&mut stored, &chain_client).await?;
+    {
+        let _store = app_state.credential_store_lock.lock().await;
+        save_credential(stored.clone())?;
+    }
+    Ok(HttpResponse::Ok().json(stored))
+}
+
+#[delete("/{root_hash}")]
+async fn delete_credential(
+    app_state: web::Data<AppState>,
+    root_hash: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let _store = app_state.credential_store_lock.lock().await;
+    let stored = remove_credential(&root_hash)?
+        .ok_or_else(|| CredentialAPIError::CredentialNotFound(root_hash.to_string()))?;
+
+    log::info!("Credential {} removed", stored.credential.root_hash);
+    Ok(HttpResponse::Ok().json(stored))
+}
+
+pub fn get_credential_store_scope() -> Scope {
+    web::scope("/api/v1/credentials")
+        .service(get_stored_credentials)
+        .service(get_stored_credential_by_hash)
+        .service(add_credential)
+        .service(refresh_credential)
+        .service(delete_credential)
+}
@@ -0,0 +1,147 @@
+use actix_web::{get, post, web, HttpResponse, Responder, Scope};
+use serde_json::Value;
+use sp_core::H256;
+use subxt::OnlineClient;
+
+use crate::{
+    device::{
+        file_manager::{get_ctype_catalog, save_ctype},
+        key_manager::KeyManager,
+    },
+    error::ServerError,
+    kilt::{
+        connect,
+        ctype::{
+            add_ctype_call, ctype_hash, ctype_id, fetch_ctype_from_chain, is_ctype_on_chain,
+            validate_claim_contents, with_ctype_id,
+        },
+        error::{CTypeError, TxError},
+        tx::DidCallBatch,
+        KiltConfig,
+    },
+    AppState,
+};
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CTypeRegistration {
+    tx: String,
+    ctype_hash: String,
+    ctype_id: String,
+}
+
+pub(crate) fn parse_ctype_hash(ctype_hash: &str) -> Result<H256, TxError> {
+    let bytes: [u8; 32] = hex::decode(ctype_hash.trim_start_matches("0x").trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| CTypeError::Hash(ctype_hash.to_string()))?;
+    Ok(H256::from(bytes))
+}
+
+/// Loads the CType from the local catalog. Unknown CTypes are fetched from the chain and added to the catalog.
+pub(crate) async fn load_ctype(
+    hash: H256,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<Value, ServerError> {
+    let key = format!("{:?}", hash);
+    if let Some(ctype) = get_ctype_catalog()?.remove(&key) {
+        return Ok(ctype);
+    }
+
+    let ctype = fetch_ctype_from_chain(hash, chain_client)
+        .await
+        .map_err(TxError::from)?
+        .ok_or_else(|| TxError::from(CTypeError::NotFound(key.clone())))?;
+    save_ctype(&key, &ctype)?;
+    Ok(ctype)
+}
+
+/// Checks the claim contents against the schema of its CType.
+pub(crate) async fn validate_claim(
+    ctype_hash: &str,
+    contents: &Value,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<(), ServerError> {
+    let ctype = load_ctype(parse_ctype_hash(ctype_hash)?, chain_client).await?;
+    validate_claim_contents(&ctype, contents).map_err(TxError::from)?;
+    Ok(())
+}
+
+#[get("")]
+async fn get_ctypes() -> Result<impl Responder, ServerError> {
+    let catalog = get_ctype_catalog()?;
+    Ok(HttpResponse::Ok().json(catalog))
+}
+
+#[get("/{ctype_hash}")]
+async fn get_ctype(
+    app_state: web::Data<AppState>,
+    ctype_hash: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let hash = parse_ctype_hash(&ctype_hash)?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let ctype = load_ctype(hash, &chain_client).await?;
+    Ok(HttpResponse::Ok().json(ctype))
+}
+
+/// Returns the CType with its `$id` computed from the hash.
+#[post("/hash")]
+async fn hash_ctype(ctype: web::Json<Value>) -> Result<impl Responder, ServerError> {
+    let ctype = with_ctype_id(&ctype).map_err(TxError::from)?;
+    Ok(HttpResponse::Ok().json(ctype))
+}
+
+/// Registers the CType on chain with the attester DID and adds it to the catalog.
+#[post("")]
+async fn register_ctype(
+    app_state: web::Data<AppState>,
+    ctype: web::Json<Value>,
+) -> Result<impl Responder, ServerError> {
+    let ctype = ctype.into_inner();
+    let hash = ctype_hash(&ctype).map_err(TxError::from)?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    if is_ctype_on_chain(hash, &chain_client)
+        .await
+        .map_err(TxError::from)?
+    {
+        return Err(TxError::from(CTypeError::AlreadyRegistered(ctype_id(&hash))).into());
+    }
+
+    let payer = app_state
+        .key_manager
+        .lock()
+        .await
+        .get_payment_account_signer();
+
+    let call = add_ctype_call(&ctype).map_err(TxError::from)?;
+    let events = DidCallBatch::new()
+        .with_call(call)
+        .submit_for(
+            &app_state.did_attester,
+            &payer,
+            &app_state.signer,
+            &chain_client,
+        )
+        .await
+        .map_err(TxError::from)?;
+
+    let ctype = with_ctype_id(&ctype).map_err(TxError::from)?;
+    save_ctype(&format!("{:?}", hash), &ctype)?;
+
+    log::info!("CType {} registered", ctype_id(&hash));
+
+    Ok(HttpResponse::Ok().json(CTypeRegistration {
+        tx: format!("0x{}", hex::encode(events.extrinsic_hash())),
+        ctype_hash: format!("{:?}", hash),
+        ctype_id: ctype_id(&hash),
+    }))
+}
+
+pub fn get_ctype_scope() -> Scope {
+    web::scope("/api/v1/ctype")
+        .service(get_ctypes)
+        .service(hash_ctype)
+        .service(get_ctype)
+        .service(register_ctype)
+}
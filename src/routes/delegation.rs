@@ -0,0 +1,232 @@
+use actix_web::{delete, get, post, web, HttpResponse, Responder, Scope};
+use sp_core::H256;
+
+use crate::{
+    device::key_manager::KeyManager,
+    dto::{
+        AddDelegationRequest, DelegateKeyType, DelegateSignature, DelegationHierarchyRequest,
+        DelegationSigningData, DelegationSigningRequest, DelegationTxResponse,
+    },
+    error::ServerError,
+    kilt::{
+        connect,
+        delegation::{
+            add_delegation_call, create_hierarchy_call, delegation_creation_hash,
+            parse_delegation_id, query_delegation_node, random_delegation_id,
+            revoke_delegation_call, DelegationNodeDetails,
+        },
+        error::{DelegationError, TxError},
+        runtime::runtime_types::{
+            did::did_details::DidSignature,
+            sp_core::{ed25519, sr25519},
+        },
+        tx::DidCallBatch,
+        KiltConfig,
+    },
+    routes::admin::Admin,
+    AppState,
+};
+
+fn parse_delegate_signature(
+    signature: &DelegateSignature,
+) -> Result<DidSignature, DelegationError> {
+    let bytes: [u8; 64] = hex::decode(signature.signature.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| DelegationError::Signature(signature.signature.clone()))?;
+
+    Ok(match signature.key_type {
+        DelegateKeyType::Sr25519 => DidSignature::Sr25519(sr25519::Signature(bytes)),
+        DelegateKeyType::Ed25519 => DidSignature::Ed25519(ed25519::Signature(bytes)),
+    })
+}
+
+async fn get_delegation_node(
+    delegation_id: H256,
+    chain_client: &subxt::OnlineClient<KiltConfig>,
+) -> Result<DelegationNodeDetails, TxError> {
+    query_delegation_node(delegation_id, chain_client)
+        .await?
+        .ok_or_else(|| DelegationError::NotFound(format!("{:?}", delegation_id)).into())
+}
+
+/// Submits the delegation calls as the attester DID, signed with its delegation key.
+async fn submit_delegation_call(
+    app_state: &AppState,
+    batch: DidCallBatch,
+    chain_client: &subxt::OnlineClient<KiltConfig>,
+) -> Result<String, TxError> {
+    let payer = app_state
+        .key_manager
+        .lock()
+        .await
+        .get_payment_account_signer();
+
+    let events = batch
+        .submit_for(
+            &app_state.did_attester,
+            &payer,
+            &app_state.delegation_signer,
+            chain_client,
+        )
+        .await?;
+    Ok(format!("0x{}", hex::encode(events.extrinsic_hash())))
+}
+
+/// Creates a new delegation hierarchy for the CType, owned by the attester DID.
+#[post("")]
+async fn create_hierarchy(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    request: web::Json<DelegationHierarchyRequest>,
+) -> Result<impl Responder, ServerError> {
+    let ctype_hash = hex::decode(request.ctype_hash.trim_start_matches("0x").trim())?;
+    if ctype_hash.len() != 32 {
+        Err(actix_web::error::ErrorBadRequest(
+            "CType hash has a wrong format",
+        ))?
+    }
+
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let root_node_id = random_delegation_id();
+    let batch = DidCallBatch::new().with_call(create_hierarchy_call(
+        root_node_id,
+        H256::from_slice(&ctype_hash),
+    ));
+    let tx = submit_delegation_call(&app_state, batch, &chain_client).await?;
+
+    log::info!("Delegation hierarchy {:?} created", root_node_id);
+
+    Ok(HttpResponse::Ok().json(DelegationTxResponse {
+        tx,
+        delegation_id: format!("{:?}", root_node_id),
+    }))
+}
+
+#[get("/{delegation_id}")]
+async fn get_delegation(
+    app_state: web::Data<AppState>,
+    delegation_id: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let delegation_id = parse_delegation_id(&delegation_id).map_err(TxError::from)?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let node = get_delegation_node(delegation_id, &chain_client).await?;
+    Ok(HttpResponse::Ok().json(node))
+}
+
+/// Returns a new delegation id and the hash the delegate has to sign to accept a delegation below `parent_id`.
+/// Only full DIDs can be delegates, as the delegation node is stored on chain.
+#[post("/{parent_id}/request")]
+async fn request_delegation(
+    app_state: web::Data<AppState>,
+    parent_id: web::Path<String>,
+    request: web::Json<DelegationSigningRequest>,
+) -> Result<impl Responder, ServerError> {
+    let parent_id = parse_delegation_id(&parent_id).map_err(TxError::from)?;
+    request
+        .delegate
+        .full_account_id()
+        .map_err(|e| TxError::Did(e.into()))?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let parent = get_delegation_node(parent_id, &chain_client).await?;
+    let hierarchy_root_id =
+        parse_delegation_id(&parent.hierarchy_root_id).map_err(TxError::from)?;
+
+    let delegation_id = random_delegation_id();
+    let hash = delegation_creation_hash(
+        delegation_id,
+        hierarchy_root_id,
+        parent_id,
+        &request.permissions,
+    );
+
+    let request = request.into_inner();
+    Ok(HttpResponse::Ok().json(DelegationSigningData {
+        delegation_id: format!("{:?}", delegation_id),
+        delegate: request.delegate,
+        hash: format!("{:?}", hash),
+    }))
+}
+
+/// Adds a delegation node below `parent_id` for the delegate, using the signature of the delegate.
+#[post("/{parent_id}/children")]
+async fn add_delegation(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    parent_id: web::Path<String>,
+    request: web::Json<AddDelegationRequest>,
+) -> Result<impl Responder, ServerError> {
+    let parent_id = parse_delegation_id(&parent_id).map_err(TxError::from)?;
+    let delegation_id = parse_delegation_id(&request.delegation_id).map_err(TxError::from)?;
+    let delegate_signature = parse_delegate_signature(&request.signature).map_err(TxError::from)?;
+    let delegate = request
+        .delegate
+        .full_account_id()
+        .map_err(|e| TxError::Did(e.into()))?
+        .clone();
+
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let parent = get_delegation_node(parent_id, &chain_client).await?;
+    if parent.revoked {
+        return Err(TxError::from(DelegationError::Revoked(parent.id)).into());
+    }
+
+    let batch = DidCallBatch::new().with_call(add_delegation_call(
+        delegation_id,
+        parent_id,
+        delegate,
+        &request.permissions,
+        delegate_signature,
+    ));
+    let tx = submit_delegation_call(&app_state, batch, &chain_client).await?;
+
+    log::info!(
+        "Delegation {:?} added for {}",
+        delegation_id,
+        request.delegate
+    );
+
+    Ok(HttpResponse::Ok().json(DelegationTxResponse {
+        tx,
+        delegation_id: format!("{:?}", delegation_id),
+    }))
+}
+
+/// Revokes the delegation node and all of its children.
+#[delete("/{delegation_id}")]
+async fn revoke_delegation(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    delegation_id: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let delegation_id = parse_delegation_id(&delegation_id).map_err(TxError::from)?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let node = get_delegation_node(delegation_id, &chain_client).await?;
+    if node.revoked {
+        return Err(TxError::from(DelegationError::Revoked(node.id)).into());
+    }
+
+    let call = revoke_delegation_call(delegation_id, &chain_client).map_err(TxError::from)?;
+    let tx = submit_delegation_call(
+        &app_state,
+        DidCallBatch::new().with_call(call),
+        &chain_client,
+    )
+    .await?;
+
+    log::info!("Delegation {:?} revoked", delegation_id);
+
+    Ok(HttpResponse::Ok().json(DelegationTxResponse {
+        tx,
+        delegation_id: format!("{:?}", delegation_id),
+    }))
+}
+
+pub fn get_delegation_scope() -> Scope {
+    web::scope("/api/v1/delegation")
+        .service(create_hierarchy)
+        .service(get_delegation)
+        .service(request_delegation)
+        .service(add_delegation)
+        .service(revoke_delegation)
+}
@@ -1,14 +1,19 @@
-use actix_web::{delete, get, post, web, HttpResponse, Responder, Scope};
-use subxt::ext::sp_core::crypto::Ss58Codec;
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder, Scope};
+use subxt::utils::AccountId32;
 
 use crate::{
-    device::{file_manager::BASE_CLAIM_PATH, key_manager::KeyManager},
-    dto::{DidAddress, TxResponse},
+    device::{file_manager::clear_credentials, key_manager::KeyManager},
+    dto::{DidAddress, ServiceEndpointUpdate, TxResponse},
     error::ServerError,
     kilt::{
         connect,
-        did_helper::{query_did_doc, ADDRESS_FORMAT, DID_PREFIX},
-        tx::create_did,
+        did_helper::query_did_doc,
+        did_uri::DidUri,
+        error::{ServiceEndpointError, TxError},
+        light_did::{LightDid, LightDidAuthKeyType},
+        service_endpoint::{get_service_endpoints, ServiceEndpoint, ServiceEndpointLimits},
+        tx::{add_service_endpoint_call, create_did, remove_service_endpoint_call, DidCallBatch},
+        web3_name::query_web3_name,
     },
     AppState,
 };
@@ -26,16 +31,9 @@ async fn register_device_did(
     let tx = format!("0x{}", hex::encode(extrinsic_hash));
     log::info!("Tx hash: {}", tx);
 
-    let did = did_auth_signer
-        .account_id()
-        .to_ss58check_with_version(ADDRESS_FORMAT.into());
+    let did = DidUri::full(did_auth_signer.account_id().clone());
 
-    let formatted_did = format!("{}{}", DID_PREFIX, did);
-
-    Ok(HttpResponse::Ok().json(TxResponse {
-        tx,
-        did: formatted_did,
-    }))
+    Ok(HttpResponse::Ok().json(TxResponse { tx, did }))
 }
 
 #[get("")]
@@ -44,13 +42,161 @@ async fn get_did(app_state: web::Data<AppState>) -> Result<impl Responder, Serve
     let did_auth_signer = &keys.get_did_auth_signer();
     let chain_client = connect(&app_state.wss_endpoint).await?;
 
-    let did = did_auth_signer
-        .account_id()
-        .to_ss58check_with_version(ADDRESS_FORMAT.into());
+    let did_address: AccountId32 = did_auth_signer.account_id().clone().into();
+    let did = DidUri::Full(did_address.clone());
 
     query_did_doc(&did, &chain_client).await?;
+    let web3_name = query_web3_name(&did_address, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+
+    Ok(HttpResponse::Ok().json(DidAddress { did, web3_name }))
+}
+
+/// Returns the light DID of the device. It can be used before the full DID is registered.
+#[get("/light")]
+async fn get_light_did(app_state: web::Data<AppState>) -> Result<impl Responder, ServerError> {
+    let keys = app_state.key_manager.lock().await;
+    let did_auth_signer = keys.get_did_auth_signer();
+
+    let light_did = LightDid::new(
+        LightDidAuthKeyType::Sr25519,
+        did_auth_signer.account_id().clone().into(),
+    );
+    let did = DidUri::light(light_did).map_err(|e| TxError::Did(e.into()))?;
+
     Ok(HttpResponse::Ok().json(DidAddress {
-        did: format!("{}{}", DID_PREFIX, did),
+        did,
+        web3_name: None,
+    }))
+}
+
+#[get("/service")]
+async fn get_services(app_state: web::Data<AppState>) -> Result<impl Responder, ServerError> {
+    let keys = app_state.key_manager.lock().await;
+    let did_auth_signer = keys.get_did_auth_signer();
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let endpoints =
+        get_service_endpoints(&did_auth_signer.account_id().clone().into(), &chain_client).await?;
+    Ok(HttpResponse::Ok().json(endpoints))
+}
+
+#[get("/service/limits")]
+async fn get_service_limits(app_state: web::Data<AppState>) -> Result<impl Responder, ServerError> {
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let limits = ServiceEndpointLimits::fetch(&chain_client)?;
+    Ok(HttpResponse::Ok().json(limits))
+}
+
+#[post("/service")]
+async fn add_service(
+    app_state: web::Data<AppState>,
+    endpoint: web::Json<ServiceEndpoint>,
+) -> Result<impl Responder, ServerError> {
+    let keys = app_state.key_manager.lock().await;
+    let did_auth_signer = keys.get_did_auth_signer();
+    let submitter_signer = keys.get_payment_account_signer();
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let limits = ServiceEndpointLimits::fetch(&chain_client)?;
+    limits.validate(&endpoint).map_err(TxError::from)?;
+
+    let did_address = did_auth_signer.account_id().clone().into();
+    let endpoints = get_service_endpoints(&did_address, &chain_client).await?;
+    if endpoints.iter().any(|e| e.id == endpoint.id) {
+        return Err(TxError::from(ServiceEndpointError::AlreadyExists(endpoint.id.clone())).into());
+    }
+    if endpoints.len() >= limits.max_services as usize {
+        return Err(
+            TxError::from(ServiceEndpointError::TooManyServices(limits.max_services)).into(),
+        );
+    }
+
+    let events = DidCallBatch::new()
+        .with_call(add_service_endpoint_call(&endpoint))
+        .submit(&submitter_signer, &did_auth_signer, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+
+    log::info!("Service endpoint {} added", endpoint.id);
+
+    Ok(HttpResponse::Ok().json(TxResponse {
+        tx: format!("0x{}", hex::encode(events.extrinsic_hash())),
+        did: DidUri::Full(did_address),
+    }))
+}
+
+/// Replaces the types and URLs of an existing service endpoint.
+/// The runtime has no update call, so the endpoint is removed and added again in a single batch.
+#[put("/service/{id}")]
+async fn update_service(
+    app_state: web::Data<AppState>,
+    service_id: web::Path<String>,
+    update: web::Json<ServiceEndpointUpdate>,
+) -> Result<impl Responder, ServerError> {
+    let keys = app_state.key_manager.lock().await;
+    let did_auth_signer = keys.get_did_auth_signer();
+    let submitter_signer = keys.get_payment_account_signer();
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let update = update.into_inner();
+    let endpoint = ServiceEndpoint {
+        id: service_id.into_inner(),
+        types: update.types,
+        urls: update.urls,
+    };
+    let limits = ServiceEndpointLimits::fetch(&chain_client)?;
+    limits.validate(&endpoint).map_err(TxError::from)?;
+
+    let did_address = did_auth_signer.account_id().clone().into();
+    let endpoints = get_service_endpoints(&did_address, &chain_client).await?;
+    if !endpoints.iter().any(|e| e.id == endpoint.id) {
+        return Err(TxError::from(ServiceEndpointError::NotFound(endpoint.id)).into());
+    }
+
+    let events = DidCallBatch::new()
+        .with_call(remove_service_endpoint_call(&endpoint.id))
+        .with_call(add_service_endpoint_call(&endpoint))
+        .submit(&submitter_signer, &did_auth_signer, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+
+    log::info!("Service endpoint {} updated", endpoint.id);
+
+    Ok(HttpResponse::Ok().json(TxResponse {
+        tx: format!("0x{}", hex::encode(events.extrinsic_hash())),
+        did: DidUri::Full(did_address),
+    }))
+}
+
+#[delete("/service/{id}")]
+async fn remove_service(
+    app_state: web::Data<AppState>,
+    service_id: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let keys = app_state.key_manager.lock().await;
+    let did_auth_signer = keys.get_did_auth_signer();
+    let submitter_signer = keys.get_payment_account_signer();
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let did_address = did_auth_signer.account_id().clone().into();
+    let endpoints = get_service_endpoints(&did_address, &chain_client).await?;
+    if !endpoints.iter().any(|e| e.id == *service_id) {
+        return Err(TxError::from(ServiceEndpointError::NotFound(service_id.into_inner())).into());
+    }
+
+    let events = DidCallBatch::new()
+        .with_call(remove_service_endpoint_call(&service_id))
+        .submit(&submitter_signer, &did_auth_signer, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+
+    log::info!("Service endpoint {} removed", service_id);
+
+    Ok(HttpResponse::Ok().json(TxResponse {
+        tx: format!("0x{}", hex::encode(events.extrinsic_hash())),
+        did: DidUri::Full(did_address),
     }))
 }
 
@@ -66,7 +212,9 @@ async fn reset(app_state: web::Data<AppState>) -> Result<impl Responder, ServerE
     let mut key_manager = app_state.key_manager.lock().await;
     *key_manager = new_key_manager;
 
-    let _ = tokio::fs::remove_file(BASE_CLAIM_PATH).await;
+    // the credentials are bound to the previous DID
+    let _store = app_state.credential_store_lock.lock().await;
+    clear_credentials()?;
 
     Ok(HttpResponse::Ok())
 }
@@ -75,5 +223,11 @@ pub fn get_did_scope() -> Scope {
     web::scope("/api/v1/did")
         .service(reset)
         .service(get_did)
+        .service(get_light_did)
         .service(register_device_did)
+        .service(get_service_limits)
+        .service(get_services)
+        .service(add_service)
+        .service(update_service)
+        .service(remove_service)
 }
@@ -0,0 +1,139 @@
+use actix_web::{delete, get, post, web, HttpResponse, Responder, Scope};
+use subxt::{ext::sp_core::crypto::Ss58Codec, utils::AccountId32};
+
+use crate::{
+    device::key_manager::KeyManager,
+    dto::TxResponse,
+    error::ServerError,
+    kilt::{
+        connect,
+        did_lookup::{
+            associate_account_call, query_connected_accounts, query_connected_did,
+            remove_account_association_call, DidConnections,
+        },
+        did_uri::DidUri,
+        error::{DidLookupError, TxError},
+        tx::DidCallBatch,
+    },
+    AppState,
+};
+
+/// Returns the accounts linked to the device DID.
+#[get("")]
+async fn get_connected_accounts(
+    app_state: web::Data<AppState>,
+) -> Result<impl Responder, ServerError> {
+    let keys = app_state.key_manager.lock().await;
+    let did_auth_signer = keys.get_did_auth_signer();
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let did_address: AccountId32 = did_auth_signer.account_id().clone().into();
+    let accounts = query_connected_accounts(&did_address, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+
+    Ok(HttpResponse::Ok().json(DidConnections {
+        did: DidUri::Full(did_address),
+        accounts,
+    }))
+}
+
+/// Returns the DID an account is linked to.
+#[get("/{account}")]
+async fn get_connected_did(
+    app_state: web::Data<AppState>,
+    account: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let account_id = subxt::ext::sp_core::crypto::AccountId32::from_ss58check(&account)
+        .map_err(|_| TxError::from(DidLookupError::Address(account.to_string())))?;
+    let account_id = AccountId32::from(<[u8; 32]>::from(account_id));
+
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let did = query_connected_did(&account_id, &chain_client)
+        .await
+        .map_err(TxError::from)?
+        .ok_or_else(|| TxError::from(DidLookupError::NotLinked(account.to_string())))?;
+
+    Ok(HttpResponse::Ok().json(DidConnections {
+        did,
+        accounts: vec![account.into_inner()],
+    }))
+}
+
+/// Links the payment account to the device DID. The payment account signs the link and pays the deposit.
+#[post("")]
+async fn link_payment_account(
+    app_state: web::Data<AppState>,
+) -> Result<impl Responder, ServerError> {
+    let keys = app_state.key_manager.lock().await;
+    let did_auth_signer = keys.get_did_auth_signer();
+    let submitter_signer = keys.get_payment_account_signer();
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let payment_account: AccountId32 = submitter_signer.account_id().clone().into();
+    let connected_did = query_connected_did(&payment_account, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+    if let Some(connected_did) = connected_did {
+        return Err(TxError::from(DidLookupError::AlreadyLinked(connected_did.to_string())).into());
+    }
+
+    let did_address: AccountId32 = did_auth_signer.account_id().clone().into();
+    let call = associate_account_call(&did_address, &submitter_signer, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+    let events = DidCallBatch::new()
+        .with_call(call)
+        .submit(&submitter_signer, &did_auth_signer, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+
+    log::info!("Payment account linked to the DID");
+
+    Ok(HttpResponse::Ok().json(TxResponse {
+        tx: format!("0x{}", hex::encode(events.extrinsic_hash())),
+        did: DidUri::Full(did_address),
+    }))
+}
+
+/// Removes the link between the payment account and the device DID.
+#[delete("")]
+async fn unlink_payment_account(
+    app_state: web::Data<AppState>,
+) -> Result<impl Responder, ServerError> {
+    let keys = app_state.key_manager.lock().await;
+    let did_auth_signer = keys.get_did_auth_signer();
+    let submitter_signer = keys.get_payment_account_signer();
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let did_address: AccountId32 = did_auth_signer.account_id().clone().into();
+    let did = DidUri::Full(did_address);
+    let payment_account: AccountId32 = submitter_signer.account_id().clone().into();
+    let connected_did = query_connected_did(&payment_account, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+    if connected_did.as_ref() != Some(&did) {
+        return Err(TxError::from(DidLookupError::NotLinked(did.to_string())).into());
+    }
+
+    let events = DidCallBatch::new()
+        .with_call(remove_account_association_call(&payment_account))
+        .submit(&submitter_signer, &did_auth_signer, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+
+    log::info!("Payment account unlinked from the DID");
+
+    Ok(HttpResponse::Ok().json(TxResponse {
+        tx: format!("0x{}", hex::encode(events.extrinsic_hash())),
+        did,
+    }))
+}
+
+pub fn get_did_lookup_scope() -> Scope {
+    web::scope("/api/v1/lookup")
+        .service(get_connected_accounts)
+        .service(get_connected_did)
+        .service(link_payment_account)
+        .service(unlink_payment_account)
+}
@@ -1,31 +1,81 @@
 use serde::{Deserialize, Serialize};
 use sodiumoxide::crypto::box_::Nonce;
 use std::collections::HashMap;
+use subxt::ext::sp_core::{sr25519, Pair};
 
-use crate::utils::{hex_nonce, prefixed_hex};
+use crate::{
+    kilt::{
+        credential_integrity::{
+            calculate_root_hash, claim_statements, hash_statement, presentation_signing_data,
+            property_statement, verify_claim_hashes,
+        },
+        did_uri::{DidKeyUri, DidUri},
+        error::{CredentialAPIError, CredentialIntegrityError},
+    },
+    utils::{hex_nonce, prefixed_hex},
+};
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Claim {
     #[serde(rename = "cTypeHash")]
     pub ctype_hash: String,
-    contents: serde_json::Value,
-    pub owner: String,
+    pub contents: serde_json::Value,
+    pub owner: DidUri,
 }
 
+/// Body of a KILT message, tagged with the message type of the messaging spec.
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct MessageBody<T> {
-    #[serde(rename = "type")]
-    pub type_: String,
-    pub content: T,
+#[serde(tag = "type", content = "content", rename_all = "kebab-case")]
+pub enum MessageBody {
+    Error(ErrorMessageContent),
+    Reject(ErrorMessageContent),
+    RequestTerms(PartialClaim),
+    SubmitTerms(SubmitTermsMessageContent),
+    RejectTerms(RejectTermsMessageContent),
+    RequestAttestation(RequestAttestationMessageContent),
+    SubmitAttestation(SubmitAttestationMessageContent),
+    /// Root hash of the rejected credential
+    RejectAttestation(String),
+    RequestPayment(RequestPaymentMessageContent),
+    ConfirmPayment(ConfirmPaymentMessageContent),
+    RequestCredential(RequestCredentialMessageContent),
+    SubmitCredential(Vec<CredentialPresentation>),
+    /// Ids of the accepted CTypes
+    AcceptCredential(Vec<String>),
+    /// Ids of the rejected CTypes
+    RejectCredential(Vec<String>),
+}
+
+impl MessageBody {
+    /// Returns the message type as it is serialized.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            MessageBody::Error(_) => "error",
+            MessageBody::Reject(_) => "reject",
+            MessageBody::RequestTerms(_) => "request-terms",
+            MessageBody::SubmitTerms(_) => "submit-terms",
+            MessageBody::RejectTerms(_) => "reject-terms",
+            MessageBody::RequestAttestation(_) => "request-attestation",
+            MessageBody::SubmitAttestation(_) => "submit-attestation",
+            MessageBody::RejectAttestation(_) => "reject-attestation",
+            MessageBody::RequestPayment(_) => "request-payment",
+            MessageBody::ConfirmPayment(_) => "confirm-payment",
+            MessageBody::RequestCredential(_) => "request-credential",
+            MessageBody::SubmitCredential(_) => "submit-credential",
+            MessageBody::AcceptCredential(_) => "accept-credential",
+            MessageBody::RejectCredential(_) => "reject-credential",
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Message<T> {
-    pub body: MessageBody<T>,
+pub struct Message {
+    pub body: MessageBody,
+    /// Unix timestamp in milliseconds
     #[serde(rename = "createdAt")]
     pub created_at: u64,
-    pub sender: String,
-    pub receiver: String,
+    pub sender: DidUri,
+    pub receiver: DidUri,
     #[serde(rename = "messageId")]
     pub message_id: String,
     #[serde(rename = "inReplyTo")]
@@ -33,6 +83,27 @@ pub struct Message<T> {
     pub references: Option<Vec<String>>,
 }
 
+impl Message {
+    pub fn new(body: MessageBody, sender: DidUri, receiver: DidUri) -> Self {
+        Message {
+            body,
+            created_at: chrono::Utc::now().timestamp_millis() as u64,
+            sender,
+            receiver,
+            message_id: uuid::Uuid::new_v4().to_string(),
+            in_reply_to: None,
+            references: None,
+        }
+    }
+
+    /// Marks the message as the answer to the message with the id.
+    pub fn with_reply_to(mut self, message_id: &str) -> Self {
+        self.in_reply_to = Some(message_id.to_string());
+        self.references = Some(vec![message_id.to_string()]);
+        self
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EncryptedMessage {
     #[serde(rename = "ciphertext")]
@@ -41,9 +112,26 @@ pub struct EncryptedMessage {
     #[serde(with = "hex_nonce")]
     pub nonce: Nonce,
     #[serde(rename = "receiverKeyUri")]
-    pub receiver_key_uri: String,
+    pub receiver_key_uri: DidKeyUri,
     #[serde(rename = "senderKeyUri")]
-    pub sender_key_uri: String,
+    pub sender_key_uri: DidKeyUri,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ErrorMessageContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Claim of which only the CType is known for sure.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartialClaim {
+    #[serde(rename = "cTypeHash")]
+    pub ctype_hash: String,
+    pub contents: Option<serde_json::Value>,
+    pub owner: Option<DidUri>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -60,17 +148,210 @@ pub struct Credential {
     pub claim: Claim,
     claim_nonce_map: HashMap<String, String>,
     claim_hashes: Vec<String>,
-    delegation_id: Option<String>,
+    pub delegation_id: Option<String>,
     legitimations: Option<Vec<Credential>>,
     pub root_hash: String,
 }
 
+impl Credential {
+    /// Returns a copy which only reveals the given claim properties.
+    /// The nonces of the hidden properties are removed, their claim hashes stay so the root hash still matches.
+    pub fn disclose(&self, reveal: &[String]) -> Result<Credential, CredentialIntegrityError> {
+        let mut credential = self.clone();
+        let contents = credential
+            .claim
+            .contents
+            .as_object_mut()
+            .ok_or(CredentialIntegrityError::Contents)?;
+
+        let hidden: Vec<String> = contents
+            .keys()
+            .filter(|key| !reveal.contains(key))
+            .cloned()
+            .collect();
+        for key in hidden {
+            if let Some(value) = contents.remove(&key) {
+                let statement = property_statement(&self.claim.ctype_hash, &key, &value)?;
+                credential
+                    .claim_nonce_map
+                    .remove(&hash_statement(&statement));
+            }
+        }
+        Ok(credential)
+    }
+
+    /// Recomputes the claim hashes and the root hash from the claim, the nonces, the legitimations and the delegation id.
+    pub fn verify_integrity(&self) -> Result<(), CredentialIntegrityError> {
+        let statements = claim_statements(
+            &self.claim.ctype_hash,
+            &self.claim.owner,
+            &self.claim.contents,
+        )?;
+        verify_claim_hashes(&statements, &self.claim_nonce_map, &self.claim_hashes)?;
+
+        let legitimations = self.legitimations.as_deref().unwrap_or_default();
+        for legitimation in legitimations {
+            legitimation.verify_integrity()?;
+        }
+        let legitimation_root_hashes = legitimations
+            .iter()
+            .map(|legitimation| legitimation.root_hash.as_str())
+            .collect::<Vec<_>>();
+
+        let root_hash = calculate_root_hash(
+            &self.claim_hashes,
+            &legitimation_root_hashes,
+            self.delegation_id.as_deref(),
+        )?;
+        if !root_hash.eq_ignore_ascii_case(&self.root_hash) {
+            return Err(CredentialIntegrityError::RootHash(self.root_hash.clone()));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RequestAttestationMessageContent {
     pub credential: Credential,
     pub quote: Option<serde_json::Value>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectTermsMessageContent {
+    pub claim: PartialClaim,
+    pub legitimations: Vec<Credential>,
+    pub delegation_id: Option<String>,
+}
+
+/// Attestation as it is sent in a `submit-attestation` message.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attestation {
+    pub claim_hash: String,
+    #[serde(rename = "cTypeHash")]
+    pub ctype_hash: String,
+    pub owner: DidUri,
+    pub delegation_id: Option<String>,
+    pub revoked: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SubmitAttestationMessageContent {
+    pub attestation: Attestation,
+}
+
+/// Attestation of the device credential, either as `submit-attestation` message or as returned by the attester.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AttestationSubmission {
+    Message(Box<Message>),
+    Attestation(Attestation),
+}
+
+impl AttestationSubmission {
+    pub fn into_attestation(self) -> Result<Attestation, CredentialAPIError> {
+        match self {
+            AttestationSubmission::Attestation(attestation) => Ok(attestation),
+            AttestationSubmission::Message(message) => match message.body {
+                MessageBody::SubmitAttestation(content) => Ok(content.attestation),
+                body => Err(CredentialAPIError::UnexpectedMessage(body.type_name())),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestPaymentMessageContent {
+    pub claim_hash: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmPaymentMessageContent {
+    pub claim_hash: String,
+    pub tx_hash: String,
+    pub block_hash: String,
+}
+
+/// CType the verifier asks for, optionally restricted to some attesters and properties.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialRequirement {
+    #[serde(rename = "cTypeHash")]
+    pub ctype_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trusted_attesters: Option<Vec<DidUri>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_properties: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RequestCredentialMessageContent {
+    #[serde(rename = "cTypes")]
+    pub ctypes: Vec<CredentialRequirement>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+}
+
+/// Signature of the claimer over the root hash of the presentation and the challenge of the verifier.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimerSignature {
+    pub key_uri: DidKeyUri,
+    pub signature: String,
+    pub challenge: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialPresentation {
+    #[serde(flatten)]
+    pub credential: Credential,
+    pub claimer_signature: ClaimerSignature,
+}
+
+impl CredentialPresentation {
+    /// Signs the root hash of the credential and the challenge with the authentication key of the claimer.
+    pub fn new(
+        credential: Credential,
+        key_uri: DidKeyUri,
+        signer: &sr25519::Pair,
+        challenge: Option<String>,
+    ) -> Result<Self, CredentialIntegrityError> {
+        let data = presentation_signing_data(&credential.root_hash, challenge.as_deref())?;
+        let signature = signer.sign(&data);
+        Ok(CredentialPresentation {
+            credential,
+            claimer_signature: ClaimerSignature {
+                key_uri,
+                signature: format!("0x{}", hex::encode(signature.0)),
+                challenge,
+            },
+        })
+    }
+}
+
+/// Outcome of the verification of one presented credential.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresentationVerification {
+    pub root_hash: String,
+    #[serde(rename = "cTypeHash")]
+    pub ctype_hash: String,
+    pub owner: DidUri,
+    pub attester: Option<DidUri>,
+    pub verified: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VerificationResult {
+    pub verified: bool,
+    pub presentations: Vec<PresentationVerification>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UseCaseParticipationMessage {
@@ -78,6 +359,18 @@ pub struct UseCaseParticipationMessage {
     pub use_case_url: String,
     pub update_service_endpoint: bool,
     pub notify_use_case: bool,
+    /// CType of the credential sent to the use case. Defaults to any CType.
+    #[serde(rename = "cTypeHash", default)]
+    pub ctype_hash: Option<String>,
+    /// Attester of the credential sent to the use case. Defaults to any attester.
+    #[serde(default)]
+    pub attester: Option<DidUri>,
+    /// Claim properties revealed to the use case. Defaults to all properties.
+    #[serde(default)]
+    pub reveal: Option<Vec<String>>,
+    /// Challenge of the use case, signed together with the credential.
+    #[serde(default)]
+    pub challenge: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
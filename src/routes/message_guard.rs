@@ -0,0 +1,148 @@
+//! Replay protection for the encrypted messages of the credential and verifier API.
+//! A message is accepted once and only while it is fresh. A reply must answer a message we sent to the same DID,
+//! e.g. the `request-attestation` answering the `submit-terms` of `get_terms`.
+//! A message only counts as received once its request succeeded, so a request failing e.g. on a chain error
+//! can be retried with the same message.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    kilt::{did_uri::DidUri, error::MessageError},
+    routes::dto::Message,
+};
+
+/// Number of received message ids remembered. The oldest ids are forgotten first.
+const SEEN_MESSAGE_CAPACITY: usize = 10_000;
+/// Number of sent messages which can still be answered.
+const SENT_MESSAGE_CAPACITY: usize = 10_000;
+/// Tolerated clock difference for messages created in the future, in milliseconds.
+const MAX_CLOCK_SKEW: u64 = 60_000;
+
+struct SentMessage {
+    receiver: DidUri,
+    message_type: &'static str,
+}
+
+/// Received message which passed the checks and is being handled. It is only remembered as seen and its sent
+/// message as answered once the handler [commits](MessageGuard::commit) it.
+#[must_use]
+pub struct MessageTicket {
+    message_id: String,
+    in_reply_to: Option<String>,
+}
+
+pub struct MessageGuard {
+    /// Maximum age of a received message in milliseconds
+    max_age: u64,
+    seen: HashSet<String>,
+    seen_order: VecDeque<String>,
+    sent: HashMap<String, SentMessage>,
+    sent_order: VecDeque<String>,
+    /// Ids of the messages being handled and of the sent messages they answer
+    in_flight: HashSet<String>,
+}
+
+impl MessageGuard {
+    /// `max_age` is in seconds. Messages older than that are rejected, so their ids can be forgotten safely.
+    pub fn new(max_age: u64) -> Self {
+        MessageGuard {
+            max_age: max_age.saturating_mul(1000),
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            sent: HashMap::new(),
+            sent_order: VecDeque::new(),
+            in_flight: HashSet::new(),
+        }
+    }
+
+    /// Remembers a message we send, so a reply to it is accepted.
+    pub fn record_sent(&mut self, message: &Message) {
+        if self.sent_order.len() >= SENT_MESSAGE_CAPACITY {
+            if let Some(oldest) = self.sent_order.pop_front() {
+                self.sent.remove(&oldest);
+            }
+        }
+        self.sent_order.push_back(message.message_id.clone());
+        self.sent.insert(
+            message.message_id.clone(),
+            SentMessage {
+                receiver: message.receiver.clone(),
+                message_type: message.body.type_name(),
+            },
+        );
+    }
+
+    /// Checks that the received message is fresh and new. With `expected_reply_to` the message must answer
+    /// a message of that type. A sent message can only be answered once.
+    /// Until the returned ticket is committed or released, the message and the message it answers are reserved.
+    pub fn check(
+        &mut self,
+        message: &Message,
+        expected_reply_to: Option<&'static str>,
+    ) -> Result<MessageTicket, MessageError> {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        if message.created_at.saturating_add(self.max_age) < now {
+            return Err(MessageError::Stale(message.message_id.clone()));
+        }
+        if message.created_at > now.saturating_add(MAX_CLOCK_SKEW) {
+            return Err(MessageError::Future(message.message_id.clone()));
+        }
+        if self.seen.contains(&message.message_id) || self.in_flight.contains(&message.message_id) {
+            return Err(MessageError::Replayed(message.message_id.clone()));
+        }
+
+        match (&message.in_reply_to, expected_reply_to) {
+            (Some(reply_to), expected) => {
+                let sent = self
+                    .sent
+                    .get(reply_to)
+                    .filter(|sent| sent.receiver.to_full() == message.sender.to_full())
+                    .filter(|_| !self.in_flight.contains(reply_to))
+                    .ok_or_else(|| MessageError::UnknownReply(reply_to.clone()))?;
+                if let Some(expected) = expected {
+                    if sent.message_type != expected {
+                        return Err(MessageError::MissingReply(expected));
+                    }
+                }
+            }
+            (None, Some(expected)) => return Err(MessageError::MissingReply(expected)),
+            (None, None) => {}
+        }
+
+        let ticket = MessageTicket {
+            message_id: message.message_id.clone(),
+            in_reply_to: message.in_reply_to.clone(),
+        };
+        self.in_flight.insert(ticket.message_id.clone());
+        if let Some(reply_to) = &ticket.in_reply_to {
+            self.in_flight.insert(reply_to.clone());
+        }
+        Ok(ticket)
+    }
+
+    /// Remembers the message as seen and its sent message as answered, once the request was accepted.
+    pub fn commit(&mut self, ticket: MessageTicket) {
+        self.release(&ticket);
+
+        if let Some(reply_to) = &ticket.in_reply_to {
+            self.sent.remove(reply_to);
+            self.sent_order.retain(|id| id != reply_to);
+        }
+
+        if self.seen_order.len() >= SEEN_MESSAGE_CAPACITY {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen_order.push_back(ticket.message_id.clone());
+        self.seen.insert(ticket.message_id);
+    }
+
+    /// Frees the message of a failed request, so it can be sent again.
+    pub fn release(&mut self, ticket: &MessageTicket) {
+        self.in_flight.remove(&ticket.message_id);
+        if let Some(reply_to) = &ticket.in_reply_to {
+            self.in_flight.remove(reply_to);
+        }
+    }
+}
@@ -1,16 +1,38 @@
+mod admin;
+mod attestation;
+mod attestation_review;
 mod challenge;
 mod claim;
 mod credential;
+mod credential_store;
+mod ctype;
+mod delegation;
 mod did;
-mod dto;
+mod did_lookup;
+pub(crate) mod dto;
+pub(crate) mod message_guard;
+mod notification;
 mod payment;
+mod resolve;
 mod use_case;
+mod verifier;
+mod web3_name;
 mod well_known_did_config;
 
+pub use attestation::get_attestation_scope;
+pub use attestation_review::get_attestation_review_scope;
 pub use challenge::get_challenge_scope;
 pub use claim::get_claim_scope;
 pub use credential::get_credential_scope;
+pub use credential_store::get_credential_store_scope;
+pub use ctype::get_ctype_scope;
+pub use delegation::get_delegation_scope;
 pub use did::get_did_scope;
+pub use did_lookup::get_did_lookup_scope;
+pub use notification::get_notification_scope;
 pub use payment::get_payment_scope;
+pub use resolve::get_resolve_scope;
 pub use use_case::get_use_case_scope;
+pub use verifier::get_verifier_scope;
+pub use web3_name::get_web3_name_scope;
 pub use well_known_did_config::get_well_known_did_config_scope;
@@ -0,0 +1,36 @@
+use actix_web::{get, web, HttpResponse, Scope};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::AppState;
+
+/// Streams credential status changes to the UI as server-sent events.
+#[get("")]
+async fn subscribe_notifications(app_state: web::Data<AppState>) -> HttpResponse {
+    let receiver = app_state.notifications.subscribe();
+
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) => {
+                    let Ok(data) = serde_json::to_string(&notification) else {
+                        continue;
+                    };
+                    let event = web::Bytes::from(format!("data: {}\n\n", data));
+                    return Some((Ok::<_, actix_web::Error>(event), receiver));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Notification stream skipped {} notifications", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .streaming(stream)
+}
+
+pub fn get_notification_scope() -> Scope {
+    web::scope("/api/v1/notification").service(subscribe_notifications)
+}
@@ -1,17 +1,26 @@
-use actix_web::{get, post, web, HttpResponse, Responder, Scope};
+use actix_web::{delete, get, post, web, HttpResponse, Responder, Scope};
+use uuid::Uuid;
 
 use crate::{
     device::key_manager::KeyManager,
     dto::PayerAddress,
     error::ServerError,
     kilt::{
+        call_decoder::decode_call,
         connect,
         error::{FormatError, TxError},
         tx::{submit_call, WaitFor},
     },
+    routes::admin::Admin,
     AppState,
 };
 
+fn decode_hex_call(call_string: &str) -> Result<Vec<u8>, ServerError> {
+    let trimmed_call = call_string.trim_start_matches("0x");
+
+    hex::decode(trimmed_call).map_err(|e| ServerError::Tx(TxError::Format(FormatError::Hex(e))))
+}
+
 #[get("")]
 async fn get_payment_account_address(
     app_state: web::Data<AppState>,
@@ -25,24 +34,104 @@ async fn submit_extrinsic(
     app_state: web::Data<AppState>,
     body: web::Json<String>,
 ) -> Result<impl Responder, ServerError> {
+    let call = decode_hex_call(&body.0)?;
+
+    if app_state.require_call_approval {
+        let pending_call = app_state.pending_calls.lock().await.push(call)?;
+        log::info!("Call {} is waiting for approval", pending_call.id);
+        return Ok(HttpResponse::Accepted().json(pending_call));
+    }
+
     let chain_client = connect(&app_state.wss_endpoint).await?;
     let keys = app_state.key_manager.lock().await;
     let signer = keys.get_payment_account_signer();
-    let call_string = body.0;
-
-    let trimmed_call = call_string.trim_start_matches("0x");
-
-    let call = hex::decode(trimmed_call)
-        .map_err(|e| ServerError::Tx(TxError::Format(FormatError::Hex(e))))?;
 
     let tx = submit_call(&chain_client, &signer, call, WaitFor::Finalized).await?;
 
     log::info!("Tx hash: {}", tx);
-    Ok(HttpResponse::Ok())
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[post("/decode")]
+async fn decode_extrinsic(body: web::Json<String>) -> Result<impl Responder, ServerError> {
+    let call = decode_hex_call(&body.0)?;
+    let decoded_call = decode_call(&call)?;
+    Ok(HttpResponse::Ok().json(decoded_call))
+}
+
+#[get("/pending")]
+async fn get_pending_calls(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+) -> Result<impl Responder, ServerError> {
+    let pending_calls = app_state.pending_calls.lock().await.list();
+    Ok(HttpResponse::Ok().json(pending_calls))
+}
+
+async fn submit_approved_call(app_state: &AppState, call: Vec<u8>) -> Result<String, ServerError> {
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let signer = app_state
+        .key_manager
+        .lock()
+        .await
+        .get_payment_account_signer();
+    Ok(submit_call(&chain_client, &signer, call, WaitFor::Finalized).await?)
+}
+
+/// Signs and submits the call. The call stays in the queue if the submission fails.
+#[post("/pending/{id}")]
+async fn approve_pending_call(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    id: web::Path<Uuid>,
+) -> Result<impl Responder, ServerError> {
+    let call = app_state
+        .pending_calls
+        .lock()
+        .await
+        .start_submission(&id)
+        .ok_or_else(|| TxError::PendingCallNotFound(id.to_string()))?;
+
+    let result = submit_approved_call(&app_state, call).await;
+    let mut pending_calls = app_state.pending_calls.lock().await;
+    let tx = match result {
+        Ok(tx) => {
+            pending_calls.finish_submission(&id);
+            tx
+        }
+        Err(e) => {
+            pending_calls.release(&id);
+            return Err(e);
+        }
+    };
+
+    log::info!("Approved call {}. Tx hash: {}", id, tx);
+    Ok(HttpResponse::Ok().json(tx))
+}
+
+#[delete("/pending/{id}")]
+async fn reject_pending_call(
+    _admin: Admin,
+    app_state: web::Data<AppState>,
+    id: web::Path<Uuid>,
+) -> Result<impl Responder, ServerError> {
+    app_state
+        .pending_calls
+        .lock()
+        .await
+        .take(&id)
+        .ok_or_else(|| TxError::PendingCallNotFound(id.to_string()))?;
+
+    log::info!("Rejected call {}", id);
+    Ok(HttpResponse::Ok().finish())
 }
 
 pub fn get_payment_scope() -> Scope {
     web::scope("/api/v1/payment")
         .service(get_payment_account_address)
         .service(submit_extrinsic)
+        .service(decode_extrinsic)
+        .service(get_pending_calls)
+        .service(approve_pending_call)
+        .service(reject_pending_call)
 }
@@ -0,0 +1,23 @@
+use actix_web::{get, web, HttpResponse, Responder, Scope};
+use std::str::FromStr;
+
+use crate::{
+    error::ServerError,
+    kilt::{connect, did_document::resolve_did, did_uri::DidUri, error::TxError},
+    AppState,
+};
+
+#[get("/{did}")]
+async fn resolve(
+    app_state: web::Data<AppState>,
+    did: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let did = DidUri::from_str(&did).map_err(|e| TxError::Did(e.into()))?;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let resolution_result = resolve_did(&did, &chain_client).await?;
+    Ok(HttpResponse::Ok().json(resolution_result))
+}
+
+pub fn get_resolve_scope() -> Scope {
+    web::scope("/api/v1/resolve").service(resolve)
+}
@@ -1,15 +1,16 @@
 use actix_web::{get, post, web, HttpResponse, Responder, Scope};
 
 use crate::{
-    device::{file_manager::get_claim_content, key_manager::KeyManager},
-    dto::UseCaseResponse,
+    device::{file_manager::get_credentials, key_manager::KeyManager},
+    dto::{CredentialFilter, CredentialStatus, UseCaseResponse},
     error::ServerError,
-    http_client::post_use_case_participation,
+    http_client::{hex_encode, post_use_case_participation},
     kilt::{
         connect,
-        did_helper::{get_did_address, get_did_service_endpoint},
-        error::UseCaseAPIError,
-        tx::{add_service_endpoint, remove_service_endpoint},
+        did_helper::{get_did_address, get_did_service_endpoint, query_did_doc},
+        error::{CredentialAPIError, UseCaseAPIError},
+        service_endpoint::ServiceEndpoint,
+        tx::{add_service_endpoint_call, remove_service_endpoint_call, DidCallBatch},
     },
     routes::dto::*,
     AppState,
@@ -32,7 +33,10 @@ async fn participate_to_use_case(
         use_case_did_url,
         update_service_endpoint,
         notify_use_case,
-        ..
+        ctype_hash,
+        attester,
+        reveal,
+        challenge,
     } = &use_case_participation_message.0;
 
     log::debug!("Use case participation posted: {:?}", use_case_did_url);
@@ -43,33 +47,57 @@ async fn participate_to_use_case(
     let concatenated_url = format!("{}/{}", use_case_did_url, formatted_did);
 
     if *update_service_endpoint {
-        if let Some(_) =
-            get_did_service_endpoint(&formatted_did, use_case_service_endpoint_id, &chain_client)
-                .await?
+        let mut batch = DidCallBatch::new();
+
+        // Removing the old and adding the new endpoint is done atomically, so the DID never ends up without an endpoint.
+        if get_did_service_endpoint(&formatted_did, use_case_service_endpoint_id, &chain_client)
+            .await?
+            .is_some()
         {
-            remove_service_endpoint(
-                use_case_service_endpoint_id,
-                &submitter_signer,
-                &did_auth_signer,
-                &chain_client,
-            )
-            .await?;
+            batch = batch.with_call(remove_service_endpoint_call(use_case_service_endpoint_id));
         }
 
-        add_service_endpoint(
-            &concatenated_url,
-            use_case_service_endpoint_id,
-            &app_state.kilt_service_endpoint_type,
-            &submitter_signer,
-            &did_auth_signer,
-            &chain_client,
-        )
-        .await?;
+        batch
+            .with_call(add_service_endpoint_call(&ServiceEndpoint {
+                id: use_case_service_endpoint_id.to_string(),
+                types: vec![app_state.kilt_service_endpoint_type.clone()],
+                urls: vec![concatenated_url.clone()],
+            }))
+            .submit(&submitter_signer, &did_auth_signer, &chain_client)
+            .await?;
+
+        log::info!("Service endpoint with url: {:?} set", concatenated_url);
     }
 
     if *notify_use_case {
-        let credential = get_claim_content()?;
-        post_use_case_participation(use_case_url, &formatted_did, credential).await?;
+        // the newest attested credential matching the use case is sent
+        let filter = CredentialFilter {
+            ctype_hash: ctype_hash.clone(),
+            attester: attester.clone(),
+            status: Some(CredentialStatus::Attested),
+        };
+        let credential = get_credentials()?
+            .into_iter()
+            .rfind(|stored| filter.matches(stored))
+            .ok_or(UseCaseAPIError::NoCredential)?
+            .credential;
+        let credential = match reveal {
+            Some(reveal) => credential.disclose(reveal),
+            None => Ok(credential),
+        }
+        .map_err(CredentialAPIError::from)?;
+
+        let did_doc = query_did_doc(&formatted_did, &chain_client).await?;
+        let key_uri = formatted_did.key_uri(&hex_encode(did_doc.authentication_key.as_bytes()));
+        let presentation = CredentialPresentation::new(
+            credential,
+            key_uri,
+            did_auth_signer.signer(),
+            challenge.clone(),
+        )
+        .map_err(CredentialAPIError::from)?;
+
+        post_use_case_participation(use_case_url, &formatted_did, presentation).await?;
     }
 
     Ok(HttpResponse::Ok().json(use_case_did_url))
@@ -0,0 +1,323 @@
+use actix_session::Session;
+use actix_web::{get, post, web, HttpResponse, Scope};
+use rand::Rng;
+use sodiumoxide::crypto::box_;
+use sp_core::H256;
+use subxt::OnlineClient;
+
+use crate::{
+    error::ServerError,
+    kilt::{
+        attestation::{query_attestation, AttestationStatus},
+        connect,
+        credential_integrity::presentation_signing_data,
+        did_signature::verify_did_signature,
+        error::{CredentialAPIError, CredentialIntegrityError, PresentationError},
+        key_resolver::resolve_key_agreement_key,
+        KiltConfig,
+    },
+    routes::{
+        challenge::{DappSession, MAX_PENDING_CHALLENGES, MAX_SESSIONS},
+        credential::encrypt_message,
+        dto::*,
+    },
+    AppState,
+};
+
+const CREDENTIAL_CHALLENGES_KEY: &str = "credential_challenges";
+const VERIFICATION_RESULT_KEY: &str = "verification_result";
+
+/// Challenges of the requested credentials, keyed by session id.
+fn get_credential_challenges(session: &Session) -> Result<Vec<(String, String)>, ServerError> {
+    Ok(session
+        .get::<Vec<(String, String)>>(CREDENTIAL_CHALLENGES_KEY)
+        .map_err(|_| CredentialAPIError::Challenge("Session not set"))?
+        .unwrap_or_default())
+}
+
+/// Latest verification result of every session, keyed by session id.
+fn get_verification_results(
+    session: &Session,
+) -> Result<Vec<(String, VerificationResult)>, ServerError> {
+    Ok(session
+        .get::<Vec<(String, VerificationResult)>>(VERIFICATION_RESULT_KEY)
+        .map_err(|_| CredentialAPIError::Challenge("Session not set"))?
+        .unwrap_or_default())
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, CredentialIntegrityError> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|_| CredentialIntegrityError::Hex(value.to_string()))
+}
+
+/// Checks the presentation against the request: integrity, claimer signature over the challenge and the on-chain attestation.
+async fn verify_presentation(
+    app_state: &AppState,
+    presentation: &CredentialPresentation,
+    challenges: &[String],
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<AttestationStatus, PresentationError> {
+    let credential = &presentation.credential;
+    let ctype_hash = &credential.claim.ctype_hash;
+    if !app_state
+        .verifier_ctypes
+        .iter()
+        .any(|requested| requested.eq_ignore_ascii_case(ctype_hash))
+    {
+        return Err(PresentationError::CTypeNotRequested(ctype_hash.clone()));
+    }
+
+    credential.verify_integrity()?;
+
+    let claimer_signature = &presentation.claimer_signature;
+    if claimer_signature.key_uri.did().to_full() != credential.claim.owner.to_full() {
+        return Err(PresentationError::SignerMismatch {
+            key_uri: claimer_signature.key_uri.to_string(),
+            owner: credential.claim.owner.to_string(),
+        });
+    }
+    let challenge = claimer_signature
+        .challenge
+        .as_deref()
+        .filter(|challenge| challenges.iter().any(|c| c == challenge))
+        .ok_or(PresentationError::Challenge)?;
+
+    let signed_data = presentation_signing_data(&credential.root_hash, Some(challenge))?;
+    verify_did_signature(
+        &claimer_signature.key_uri,
+        &signed_data,
+        &claimer_signature.signature,
+        chain_client,
+    )
+    .await?;
+
+    let root_hash: [u8; 32] = decode_hex(&credential.root_hash)?
+        .try_into()
+        .map_err(|_| CredentialIntegrityError::Hex(credential.root_hash.clone()))?;
+    let attestation = query_attestation(H256::from(root_hash), chain_client)
+        .await?
+        .ok_or_else(|| PresentationError::NotAttested(credential.root_hash.clone()))?;
+
+    if attestation.revoked {
+        return Err(PresentationError::Revoked(credential.root_hash.clone()));
+    }
+    if !attestation.ctype_hash.eq_ignore_ascii_case(ctype_hash) {
+        return Err(PresentationError::CTypeMismatch(
+            credential.root_hash.clone(),
+        ));
+    }
+    if !app_state.verifier_trusted_attesters.is_empty()
+        && !app_state
+            .verifier_trusted_attesters
+            .contains(&attestation.attester)
+    {
+        return Err(PresentationError::UntrustedAttester(
+            attestation.attester.to_string(),
+        ));
+    }
+    Ok(attestation)
+}
+
+/// Returns the encrypted `request-credential` message for the claimer of the session.
+#[get("/request")]
+async fn request_credential(
+    app_state: web::Data<AppState>,
+    session: Session,
+    dapp_session: DappSession,
+) -> Result<HttpResponse, ServerError> {
+    if app_state.verifier_ctypes.is_empty() {
+        return Err(CredentialAPIError::Verifier("No CTypes configured").into());
+    }
+
+    let receiver_key_uri = dapp_session.0.encryption_key_uri;
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let receiver_pubkey = resolve_key_agreement_key(
+        &receiver_key_uri,
+        &chain_client,
+        &app_state.key_agreement_keys,
+    )
+    .await?;
+
+    let challenge = format!("0x{}", hex::encode(rand::thread_rng().gen::<[u8; 32]>()));
+    let trusted_attesters = (!app_state.verifier_trusted_attesters.is_empty())
+        .then(|| app_state.verifier_trusted_attesters.clone());
+    let content = RequestCredentialMessageContent {
+        ctypes: app_state
+            .verifier_ctypes
+            .iter()
+            .map(|ctype_hash| CredentialRequirement {
+                ctype_hash: ctype_hash.clone(),
+                trusted_attesters: trusted_attesters.clone(),
+                required_properties: None,
+            })
+            .collect(),
+        challenge: Some(challenge.clone()),
+    };
+
+    let msg = Message::new(
+        MessageBody::RequestCredential(content),
+        app_state.session_encryption_public_key_uri.did().clone(),
+        receiver_key_uri.did().clone(),
+    );
+
+    let mut challenges = get_credential_challenges(&session)?;
+    challenges.push((dapp_session.0.session_id, challenge));
+    if challenges.len() > MAX_PENDING_CHALLENGES {
+        challenges.remove(0);
+    }
+    session
+        .insert(CREDENTIAL_CHALLENGES_KEY, challenges)
+        .map_err(|_| CredentialAPIError::Challenge("Could not insert challenge"))?;
+
+    let response = encrypt_message(&app_state, &msg, receiver_key_uri, &receiver_pubkey)?;
+    app_state.message_guard.lock().await.record_sent(&msg);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Decrypts the `submit-credential` message, verifies the presentations and stores the result in the session.
+#[post("")]
+async fn submit_credential(
+    app_state: web::Data<AppState>,
+    session: Session,
+    dapp_session: DappSession,
+    encrypted_message: web::Json<EncryptedMessage>,
+) -> Result<HttpResponse, ServerError> {
+    let session_id = dapp_session.0.session_id;
+    let challenges: Vec<String> = get_credential_challenges(&session)?
+        .into_iter()
+        .filter(|(id, _)| *id == session_id)
+        .map(|(_, challenge)| challenge)
+        .collect();
+    if challenges.is_empty() {
+        return Err(CredentialAPIError::Verifier("No credential requested").into());
+    }
+
+    let session_key_uri = dapp_session.0.encryption_key_uri;
+    if encrypted_message.sender_key_uri.did().to_full() != session_key_uri.did().to_full() {
+        return Err(CredentialAPIError::Verifier("Sender does not match the session").into());
+    }
+
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let others_pubkey = resolve_key_agreement_key(
+        &encrypted_message.sender_key_uri,
+        &chain_client,
+        &app_state.key_agreement_keys,
+    )
+    .await?;
+
+    let decrypted_message_bytes = box_::open(
+        &encrypted_message.cipher_text,
+        &encrypted_message.nonce,
+        &others_pubkey,
+        &app_state.secret_key,
+    )
+    .map_err(|_| CredentialAPIError::Verifier("Unable to decrypt"))?;
+
+    let decrypted_message: Message = serde_json::from_slice(&decrypted_message_bytes)?;
+    let ticket = app_state
+        .message_guard
+        .lock()
+        .await
+        .check(&decrypted_message, Some("request-credential"))
+        .map_err(CredentialAPIError::from)?;
+
+    let result = handle_submission(
+        &app_state,
+        &session,
+        &session_id,
+        &challenges,
+        decrypted_message,
+        &chain_client,
+    )
+    .await;
+
+    // the request stays open if the submission could not be handled, so the claimer can send it again
+    let mut message_guard = app_state.message_guard.lock().await;
+    match result {
+        Ok(_) => message_guard.commit(ticket),
+        Err(_) => message_guard.release(&ticket),
+    }
+    Ok(HttpResponse::Ok().json(result?))
+}
+
+/// Verifies the presentations of the `submit-credential` message and stores the result for the session.
+async fn handle_submission(
+    app_state: &AppState,
+    session: &Session,
+    session_id: &str,
+    challenges: &[String],
+    decrypted_message: Message,
+    chain_client: &OnlineClient<KiltConfig>,
+) -> Result<VerificationResult, ServerError> {
+    let presentations = match decrypted_message.body {
+        MessageBody::SubmitCredential(presentations) => presentations,
+        body => {
+            return Err(CredentialAPIError::UnexpectedMessage(body.type_name()).into());
+        }
+    };
+
+    let mut results = vec![];
+    for presentation in &presentations {
+        let credential = &presentation.credential;
+        let outcome = verify_presentation(app_state, presentation, challenges, chain_client).await;
+        if let Err(e) = &outcome {
+            log::warn!("Presentation {} rejected: {}", credential.root_hash, e);
+        }
+        results.push(PresentationVerification {
+            root_hash: credential.root_hash.clone(),
+            ctype_hash: credential.claim.ctype_hash.clone(),
+            owner: credential.claim.owner.clone(),
+            attester: outcome.as_ref().ok().map(|a| a.attester.clone()),
+            verified: outcome.is_ok(),
+            error: outcome.err().map(|e| e.to_string()),
+        });
+    }
+
+    let result = VerificationResult {
+        verified: !results.is_empty() && results.iter().all(|r| r.verified),
+        presentations: results,
+    };
+
+    // a challenge is only valid for one submission
+    let mut all_challenges = get_credential_challenges(session)?;
+    all_challenges.retain(|(id, challenge)| {
+        id != session_id
+            || !presentations
+                .iter()
+                .any(|p| p.claimer_signature.challenge.as_ref() == Some(challenge))
+    });
+    session
+        .insert(CREDENTIAL_CHALLENGES_KEY, all_challenges)
+        .map_err(|_| CredentialAPIError::Challenge("Could not insert challenge"))?;
+
+    let mut verification_results = get_verification_results(session)?;
+    verification_results.retain(|(id, _)| id != session_id);
+    verification_results.push((session_id.to_string(), result.clone()));
+    if verification_results.len() > MAX_SESSIONS {
+        verification_results.remove(0);
+    }
+    session
+        .insert(VERIFICATION_RESULT_KEY, verification_results)
+        .map_err(|_| CredentialAPIError::Verifier("Could not store the verification result"))?;
+
+    Ok(result)
+}
+
+#[get("/result")]
+async fn get_verification_result(
+    session: Session,
+    dapp_session: DappSession,
+) -> Result<HttpResponse, ServerError> {
+    let (_, result) = get_verification_results(&session)?
+        .into_iter()
+        .find(|(id, _)| *id == dapp_session.0.session_id)
+        .ok_or(CredentialAPIError::Verifier("No credential verified"))?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+pub fn get_verifier_scope() -> Scope {
+    web::scope("/api/v1/verifier")
+        .service(request_credential)
+        .service(submit_credential)
+        .service(get_verification_result)
+}
@@ -0,0 +1,105 @@
+use actix_web::{delete, get, post, web, HttpResponse, Responder, Scope};
+use subxt::utils::AccountId32;
+
+use crate::{
+    device::key_manager::KeyManager,
+    dto::TxResponse,
+    error::ServerError,
+    kilt::{
+        connect,
+        did_uri::DidUri,
+        error::{TxError, Web3NameError},
+        tx::DidCallBatch,
+        web3_name::{
+            claim_web3_name_call, query_web3_name, query_web3_name_status, release_web3_name_call,
+        },
+    },
+    AppState,
+};
+
+#[get("/{name}")]
+async fn check_web3_name(
+    app_state: web::Data<AppState>,
+    name: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+    let status = query_web3_name_status(&name, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// Claims the web3 name for the device DID. The deposit is paid by the payment account.
+#[post("/{name}")]
+async fn claim_web3_name(
+    app_state: web::Data<AppState>,
+    name: web::Path<String>,
+) -> Result<impl Responder, ServerError> {
+    let keys = app_state.key_manager.lock().await;
+    let did_auth_signer = keys.get_did_auth_signer();
+    let submitter_signer = keys.get_payment_account_signer();
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let did_address: AccountId32 = did_auth_signer.account_id().clone().into();
+    let current_name = query_web3_name(&did_address, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+    if let Some(current_name) = current_name {
+        return Err(TxError::from(Web3NameError::AlreadyClaimed(current_name)).into());
+    }
+
+    let status = query_web3_name_status(&name, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+    if !status.available {
+        return Err(TxError::from(Web3NameError::Unavailable(name.into_inner())).into());
+    }
+
+    let events = DidCallBatch::new()
+        .with_call(claim_web3_name_call(&name))
+        .submit(&submitter_signer, &did_auth_signer, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+
+    log::info!("Web3 name {} claimed", name);
+
+    Ok(HttpResponse::Ok().json(TxResponse {
+        tx: format!("0x{}", hex::encode(events.extrinsic_hash())),
+        did: DidUri::Full(did_address),
+    }))
+}
+
+/// Releases the web3 name of the device DID and returns the deposit.
+#[delete("")]
+async fn release_web3_name(app_state: web::Data<AppState>) -> Result<impl Responder, ServerError> {
+    let keys = app_state.key_manager.lock().await;
+    let did_auth_signer = keys.get_did_auth_signer();
+    let submitter_signer = keys.get_payment_account_signer();
+    let chain_client = connect(&app_state.wss_endpoint).await?;
+
+    let did_address: AccountId32 = did_auth_signer.account_id().clone().into();
+    let name = query_web3_name(&did_address, &chain_client)
+        .await
+        .map_err(TxError::from)?
+        .ok_or(TxError::from(Web3NameError::NotClaimed))?;
+
+    let events = DidCallBatch::new()
+        .with_call(release_web3_name_call())
+        .submit(&submitter_signer, &did_auth_signer, &chain_client)
+        .await
+        .map_err(TxError::from)?;
+
+    log::info!("Web3 name {} released", name);
+
+    Ok(HttpResponse::Ok().json(TxResponse {
+        tx: format!("0x{}", hex::encode(events.extrinsic_hash())),
+        did: DidUri::Full(did_address),
+    }))
+}
+
+pub fn get_web3_name_scope() -> Scope {
+    web::scope("/api/v1/web3name")
+        .service(release_web3_name)
+        .service(check_web3_name)
+        .service(claim_web3_name)
+}